@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use crate::hash::keccak256;
+use crate::types::{Address, Edge, U256};
+
+/// Address of the Circles Hub contract on Gnosis Chain.
+pub const HUB_ADDRESS: &str = "0x29b9a7fBb8995b2423a71cC17cf9810798F6C543";
+
+pub const TRANSFER_THROUGH_SIG: &str = "transferThrough(address[],address[],address[],uint256[])";
+
+/// Returns the first four bytes of the keccak256 hash of the function signature.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Encodes a call to `transferThrough(tokenOwners, srcs, dests, wads)` on the Hub
+/// that performs the given transfers, as returned by `compute_flow`.
+pub fn encode_transfer_through(transfers: &[Edge]) -> Vec<u8> {
+    let token_owners = transfers.iter().map(|e| address_word(&e.token));
+    let srcs = transfers.iter().map(|e| address_word(&e.from));
+    let dests = transfers.iter().map(|e| address_word(&e.to));
+    let wads = transfers.iter().map(|e| uint_word(&e.capacity));
+    let arrays: Vec<Vec<[u8; 32]>> = vec![
+        token_owners.collect(),
+        srcs.collect(),
+        dests.collect(),
+        wads.collect(),
+    ];
+
+    let mut result = function_selector(TRANSFER_THROUGH_SIG).to_vec();
+    // Head: the offsets of the dynamic arrays, relative to the start of the arguments.
+    let mut offset = 32 * arrays.len();
+    for array in &arrays {
+        result.extend(uint_word(&U256::from(offset as u128)));
+        offset += 32 * (1 + array.len());
+    }
+    // Tail: length followed by the elements for each array.
+    for array in &arrays {
+        result.extend(uint_word(&U256::from(array.len() as u128)));
+        for word in array {
+            result.extend(word);
+        }
+    }
+    result
+}
+
+/// Formats bytes as a `0x`-prefixed hex string.
+pub fn to_hex(data: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + 2 * data.len());
+    out.push_str("0x");
+    for b in data {
+        write!(out, "{b:02x}").expect("");
+    }
+    out
+}
+
+fn address_word(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&address.to_bytes());
+    word
+}
+
+fn uint_word(value: &U256) -> [u8; 32] {
+    value.to_be_bytes()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selector() {
+        assert_eq!(
+            to_hex(&function_selector("transfer(address,uint256)")),
+            "0xa9059cbb"
+        );
+    }
+
+    #[test]
+    fn empty_transfer_through() {
+        let encoded = encode_transfer_through(&[]);
+        assert_eq!(encoded.len(), 4 + 8 * 32);
+        assert_eq!(encoded[..4], function_selector(TRANSFER_THROUGH_SIG));
+        let offsets = encoded[4..4 + 4 * 32]
+            .chunks(32)
+            .map(|w| w[31])
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0x80, 0xa0, 0xc0, 0xe0]);
+        assert!(encoded[4 + 4 * 32..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn transfer_through() {
+        let a = Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E");
+        let b = Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37");
+        let encoded = encode_transfer_through(&[Edge {
+            from: a,
+            to: b,
+            token: a,
+            capacity: U256::from(10),
+        }]);
+        let words = encoded[4..].chunks(32).map(to_hex).collect::<Vec<_>>();
+        let a_word = "0x00000000000000000000000011c7e86ff693e9032a0f41711b5581a04b26be2e";
+        let b_word = "0x00000000000000000000000022cedde51198d1773590311e2a340dc06b24cb37";
+        let uint = |v: u128| to_hex(&uint_word(&U256::from(v)));
+        assert_eq!(
+            words,
+            vec![
+                uint(0x80),
+                uint(0xc0),
+                uint(0x100),
+                uint(0x140),
+                uint(1),
+                a_word.to_string(),
+                uint(1),
+                a_word.to_string(),
+                uint(1),
+                b_word.to_string(),
+                uint(1),
+                uint(10),
+            ]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Options accepted by all commands.
+pub const GLOBAL_OPTIONS: &[(&str, &str)] =
+    &[("--names", "<names.csv>"), ("--output", "<json|table|csv>")];
+
+/// Description of a subcommand, used for parsing and for the usage text.
+pub struct Command {
+    pub name: &'static str,
+    pub about: &'static str,
+    /// Names of the positional arguments, all of them are required.
+    pub arguments: &'static [&'static str],
+    /// Options that take a value, as (name, placeholder).
+    pub options: &'static [(&'static str, &'static str)],
+    pub flags: &'static [&'static str],
+}
+
+impl Command {
+    pub fn usage(&self) -> String {
+        let mut usage = format!("cli {}", self.name);
+        for argument in self.arguments {
+            usage += &format!(" <{argument}>");
+        }
+        for (option, placeholder) in self.options.iter().chain(GLOBAL_OPTIONS) {
+            usage += &format!(" [{option} {placeholder}]");
+        }
+        for flag in self.flags {
+            usage += &format!(" [{flag}]");
+        }
+        usage
+    }
+
+    fn takes_value(&self, option: &str) -> bool {
+        self.options
+            .iter()
+            .chain(GLOBAL_OPTIONS)
+            .any(|(name, _)| *name == option)
+    }
+}
+
+/// The arguments of a subcommand after parsing.
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    /// Parses the arguments following the name of the command. Options can be given
+    /// anywhere, either as `--option value` or as `--option=value`.
+    pub fn parse(command: &Command, raw: &[String]) -> Result<Args, String> {
+        let mut args = Args {
+            positional: vec![],
+            options: HashMap::new(),
+            flags: HashSet::new(),
+        };
+        let mut raw = raw.iter();
+        while let Some(arg) = raw.next() {
+            if !arg.starts_with("--") {
+                args.positional.push(arg.clone());
+                continue;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if command.takes_value(name) {
+                let value = inline_value
+                    .or_else(|| raw.next().cloned())
+                    .ok_or_else(|| format!("Missing value for {name}"))?;
+                if args.options.insert(name.to_string(), value).is_some() {
+                    return Err(format!("Option {name} given more than once"));
+                }
+            } else if command.flags.contains(&name) && inline_value.is_none() {
+                args.flags.insert(name.to_string());
+            } else {
+                return Err(format!("Unknown option {arg} for {}", command.name));
+            }
+        }
+        if args.positional.len() != command.arguments.len() {
+            return Err(format!(
+                "Expected {} arguments, but got {}",
+                command.arguments.len(),
+                args.positional.len()
+            ));
+        }
+        Ok(args)
+    }
+
+    /// Returns the positional argument at `index`, which has been checked to exist.
+    pub fn arg(&self, index: usize) -> &String {
+        &self.positional[index]
+    }
+
+    pub fn option(&self, name: &str) -> Option<&String> {
+        self.options.get(name)
+    }
+
+    /// Parses the value of an option, if it is present.
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T::Err: Display,
+    {
+        self.option(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| format!("Invalid value \"{value}\" for {name}: {e}"))
+            })
+            .transpose()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COMMAND: Command = Command {
+        name: "flow",
+        about: "",
+        arguments: &["from", "to"],
+        options: &[("--max-hops", "<n>")],
+        flags: &["--verbose"],
+    };
+
+    fn parse(raw: &[&str]) -> Result<Args, String> {
+        let raw: Vec<String> = raw.iter().map(|arg| arg.to_string()).collect();
+        Args::parse(&COMMAND, &raw)
+    }
+
+    #[test]
+    fn options_and_flags() {
+        let args = parse(&["--max-hops", "3", "a", "--verbose", "b"]).unwrap();
+        assert_eq!(args.arg(0), "a");
+        assert_eq!(args.arg(1), "b");
+        assert_eq!(args.value::<u64>("--max-hops"), Ok(Some(3)));
+        assert!(args.flag("--verbose"));
+        assert_eq!(args.option("--output"), None);
+
+        let args = parse(&["a", "b", "--max-hops=4", "--output=csv"]).unwrap();
+        assert_eq!(args.value::<u64>("--max-hops"), Ok(Some(4)));
+        assert_eq!(args.option("--output").unwrap(), "csv");
+        assert!(!args.flag("--verbose"));
+        assert!(args
+            .value::<u64>("--output")
+            .unwrap_err()
+            .starts_with("Invalid value \"csv\" for --output"));
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert_eq!(
+            parse(&["a", "b", "--max-hops", "3", "--max-hops=4"]).err(),
+            Some("Option --max-hops given more than once".to_string())
+        );
+        assert_eq!(
+            parse(&["a", "b", "--max-hops"]).err(),
+            Some("Missing value for --max-hops".to_string())
+        );
+        assert_eq!(
+            parse(&["a", "b", "--quiet"]).err(),
+            Some("Unknown option --quiet for flow".to_string())
+        );
+        assert_eq!(
+            parse(&["a", "b", "--verbose=yes"]).err(),
+            Some("Unknown option --verbose=yes for flow".to_string())
+        );
+    }
+
+    #[test]
+    fn positional_count() {
+        assert_eq!(
+            parse(&["a"]).err(),
+            Some("Expected 2 arguments, but got 1".to_string())
+        );
+        assert_eq!(
+            parse(&["a", "b", "c"]).err(),
+            Some("Expected 2 arguments, but got 3".to_string())
+        );
+        // The value of an option is not counted as a positional argument.
+        assert_eq!(
+            parse(&["a", "--max-hops", "b"]).err(),
+            Some("Expected 2 arguments, but got 1".to_string())
+        );
+    }
+}
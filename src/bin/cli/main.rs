@@ -0,0 +1,739 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::process;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use pathfinder2::abi;
+use pathfinder2::graph::analytics::{self, AddressGraph};
+use pathfinder2::graph::{self, BottleneckKind, PathCost, TrustPath, UndirectedCutTree};
+use pathfinder2::io;
+use pathfinder2::replay::HubState;
+use pathfinder2::safe_db::liquidity;
+use pathfinder2::time_circles::{self, Unit};
+use pathfinder2::types::edge::{self, EdgeDB};
+use pathfinder2::types::{Address, AddressBook, Edge, FlowResult, U256};
+
+mod args;
+mod output;
+mod repl;
+
+use args::{Args, Command, GLOBAL_OPTIONS};
+use output::{OutputFormat, Table};
+
+const RPC_URL: &str = "https://rpc.gnosischain.com";
+/// Number of source addresses used to estimate the betweenness centrality.
+const BETWEENNESS_SAMPLES: usize = 200;
+/// Number of pairs used to find the accounts that limit the flow most often.
+const DEFAULT_SAMPLE_PAIRS: usize = 100;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "flow",
+        about: "Computes the maximum flow and the transfers from one address to another.",
+        arguments: &["from", "to", "edges"],
+        options: &[
+            ("--max-hops", "<n>"),
+            ("--max-flow", "<amount>"),
+            ("--max-transfers", "<n>"),
+            ("--dot", "<dotfile>"),
+            ("--unit", "<crc|tc>"),
+            ("--timestamp", "<seconds>"),
+        ],
+        flags: &["--calldata"],
+    },
+    Command {
+        name: "paths",
+        about: "Finds the k shortest paths, by number of hops or by inverse capacity.",
+        arguments: &["from", "to", "edges"],
+        options: &[("--k", "<n>"), ("--cost", "<hops|capacity>")],
+        flags: &[],
+    },
+    Command {
+        name: "explain",
+        about: "Explains which balances and trust limits limit the flow.",
+        arguments: &["from", "to", "edges"],
+        options: &[("--value", "<amount>"), ("--max-hops", "<n>")],
+        flags: &[],
+    },
+    Command {
+        name: "matrix",
+        about: "Computes the maximum flow from each source to each target. Sources and targets \
+                are comma-separated lists of addresses or @<file> with one address per line.",
+        arguments: &["edges", "sources", "targets"],
+        options: &[("--max-hops", "<n>")],
+        flags: &[],
+    },
+    Command {
+        name: "cut-tree",
+        about: "Builds the cut tree of the undirected address graph, whose minimum cuts are \
+                upper bounds for the maximum flows, and writes it to a JSON file.",
+        arguments: &["edges", "tree"],
+        options: &[],
+        flags: &[],
+    },
+    Command {
+        name: "min-cut",
+        about: "Looks up the minimum cuts between sources and targets in a cut tree. Sources \
+                and targets are given like for matrix.",
+        arguments: &["tree", "sources", "targets"],
+        options: &[],
+        flags: &[],
+    },
+    Command {
+        name: "stats",
+        about: "Reports the structure of the trust network, or the statistics of each address \
+                with --output csv or json.",
+        arguments: &["edges"],
+        options: &[("--top", "<n>")],
+        flags: &[],
+    },
+    Command {
+        name: "liquidity",
+        about: "Reports the distribution of each token, balances that cannot be moved and the \
+                accounts whose balances limit the flow between most of the sampled pairs.",
+        arguments: &["safes"],
+        options: &[("--pairs", "<n>")],
+        flags: &[],
+    },
+    Command {
+        name: "export",
+        about: "Exports all edges between addresses at most --hops (default 2) hops away to \
+                DOT, GraphML or GEXF, depending on the extension of the output file.",
+        arguments: &["edges", "address", "output"],
+        options: &[("--hops", "<n>"), ("--highlight", "<edges.json>")],
+        flags: &["--expand"],
+    },
+    Command {
+        name: "verify",
+        about: "Checks that the transfers of a flow result (JSON) can be executed by the Hub \
+                and move the flow from the sender (default: the first sender) to the receiver.",
+        arguments: &["transfers", "safes"],
+        options: &[("--sender", "<address>")],
+        flags: &[],
+    },
+    Command {
+        name: "convert",
+        about: "Converts edges or safes. The output format is taken from --to or from the \
+                extension of the output file (.csv, .json, .dot, otherwise edges-bin).",
+        arguments: &["input", "output"],
+        options: &[("--to", "<edges-bin|edges-csv|edges-json|safes-bin|dot>")],
+        flags: &[],
+    },
+    Command {
+        name: "diff",
+        about: "Lists the edges that were added, removed or changed their capacity.",
+        arguments: &["old", "new"],
+        options: &[],
+        flags: &[],
+    },
+    Command {
+        name: "repl",
+        about: "Loads the edges once and reads commands (flow, paths, neighbors, set, undo, \
+                dot, history) from stdin to explore what-if scenarios.",
+        arguments: &["edges"],
+        options: &[],
+        flags: &[],
+    },
+];
+
+fn main() {
+    let raw = env::args().skip(1).collect::<Vec<_>>();
+    let Some(name) = raw.first() else {
+        print_usage();
+        return;
+    };
+    if name == "help" || name == "--help" {
+        match raw.get(1).and_then(|name| find_command(name)) {
+            Some(command) => print_command_usage(command),
+            None => print_usage(),
+        }
+        return;
+    }
+    let Some(command) = find_command(name) else {
+        eprintln!("Error: Unknown command \"{name}\", run \"cli help\" for a list of commands.");
+        process::exit(2);
+    };
+    let args = Args::parse(command, &raw[1..]).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        eprintln!("Usage: {}", command.usage());
+        process::exit(2);
+    });
+    if let Err(e) = run(command, &args) {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+fn print_usage() {
+    println!("Usage: cli <command> <arguments> [<options>]");
+    println!("Commands:");
+    for command in COMMANDS {
+        println!("    {:10}{}", command.name, command.about);
+    }
+    println!("Run \"cli help <command>\" for the arguments and options of a command.");
+}
+
+fn print_command_usage(command: &Command) {
+    println!("Usage: {}", command.usage());
+    println!("{}", command.about);
+    println!("Edge and safe files are read in any format (binary, csv or json), which is detected from their contents.");
+    println!("Amounts are given in the smallest unit, or in CRC if they contain a decimal point or end in \"CRC\".");
+    println!(
+        "Option --names reads names of addresses from a file with lines of the form address,name."
+    );
+    println!("Names can be used instead of addresses and are shown in the output.");
+    println!(
+        "Option --output selects the output format: {} (default table).",
+        GLOBAL_OPTIONS[1].1
+    );
+}
+
+/// Settings shared by all commands.
+struct Context {
+    names: AddressBook,
+    output: OutputFormat,
+}
+
+impl Context {
+    /// Parses an address or looks up a name in the address book.
+    fn resolve(&self, input: &str) -> CliResult<Address> {
+        self.names
+            .resolve(input)
+            .map_err(|e| format!("Invalid address or unknown name \"{input}\": {e}").into())
+    }
+
+    /// Parses a comma-separated list of addresses, or reads them from a file
+    /// (one per line) if the input starts with `@`.
+    fn resolve_list(&self, input: &str) -> CliResult<Vec<Address>> {
+        let list = match input.strip_prefix('@') {
+            Some(file) => fs::read_to_string(file)
+                .map_err(|e| format!("Error reading addresses from \"{file}\": {e}"))?,
+            None => input.to_string(),
+        };
+        list.split([',', '\n'])
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(|a| self.resolve(a))
+            .collect()
+    }
+
+    fn read_edges(&self, path: &String) -> CliResult<EdgeDB> {
+        let edges =
+            io::read_edges_file(path).map_err(|e| format!("Error loading \"{path}\": {e}"))?;
+        eprintln!("Read {} edges from {path}", edges.edge_count());
+        Ok(edges)
+    }
+
+    /// Addresses are shown by name in tables and as checksummed hex otherwise.
+    fn address(&self, address: &Address) -> String {
+        match (self.output, self.names.name(address)) {
+            (OutputFormat::Table, Some(name)) => name.to_string(),
+            _ => address.to_checksummed_hex(),
+        }
+    }
+
+    /// Amounts are shown in CRC in tables and in the smallest unit otherwise.
+    fn amount(&self, amount: U256) -> String {
+        match self.output {
+            OutputFormat::Table => amount.to_crc(),
+            _ => amount.to_decimal(),
+        }
+    }
+}
+
+fn run(command: &Command, args: &Args) -> CliResult<()> {
+    let names = match args.option("--names") {
+        Some(file) => io::read_address_book(file)
+            .map_err(|e| format!("Error reading names from \"{file}\": {e}"))?,
+        None => AddressBook::default(),
+    };
+    let ctx = Context {
+        names,
+        output: args.value("--output")?.unwrap_or_default(),
+    };
+    match command.name {
+        "flow" => flow(args, &ctx),
+        "paths" => paths(args, &ctx),
+        "explain" => explain(args, &ctx),
+        "matrix" => matrix(args, &ctx),
+        "cut-tree" => cut_tree(args, &ctx),
+        "min-cut" => min_cut(args, &ctx),
+        "stats" => stats(args, &ctx),
+        "liquidity" => liquidity(args, &ctx),
+        "export" => export(args, &ctx),
+        "verify" => verify(args, &ctx),
+        "convert" => convert(args, &ctx),
+        "diff" => diff(args, &ctx),
+        "repl" => {
+            let edges = ctx.read_edges(args.arg(0))?;
+            repl::run(edges, &ctx)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Parses an amount option, see `U256::parse_amount`.
+fn amount_option(args: &Args, name: &str) -> CliResult<Option<U256>> {
+    Ok(args
+        .option(name)
+        .map(|value| {
+            U256::parse_amount(value)
+                .map_err(|e| format!("Invalid amount \"{value}\" for {name}: {e}"))
+        })
+        .transpose()?)
+}
+
+/// The JSON output of `flow`, in the format the CLI has always printed.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FlowOutput {
+    max_flow_value: U256,
+    transfer_steps: Vec<TransferStep>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    calldata: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TransferStep {
+    from: Address,
+    to: Address,
+    token: Address,
+    value: U256,
+    step: usize,
+}
+
+/// Flow results accepted by `verify`: the output of `flow` or a `compute_transfer` result.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FlowInput {
+    Cli(FlowOutput),
+    Server(FlowResult),
+}
+
+impl From<FlowInput> for FlowResult {
+    fn from(input: FlowInput) -> Self {
+        match input {
+            FlowInput::Cli(output) => FlowResult {
+                flow: output.max_flow_value,
+                transfers: output
+                    .transfer_steps
+                    .into_iter()
+                    .map(|t| Edge {
+                        from: t.from,
+                        to: t.to,
+                        token: t.token,
+                        capacity: t.value,
+                    })
+                    .collect(),
+            },
+            FlowInput::Server(result) => result,
+        }
+    }
+}
+
+fn flow(args: &Args, ctx: &Context) -> CliResult<()> {
+    let (from, to) = (ctx.resolve(args.arg(0))?, ctx.resolve(args.arg(1))?);
+    let unit = args.value("--unit")?.unwrap_or_default();
+    let timestamp = match args.value("--timestamp")? {
+        Some(timestamp) => time_circles::validate_timestamp(timestamp, time_circles::now())?,
+        None => time_circles::now(),
+    };
+    let max_flow = amount_option(args, "--max-flow")?.map_or(U256::MAX, |amount| {
+        time_circles::convert(amount, unit, Unit::Crc, timestamp)
+    });
+    let max_hops = args.value("--max-hops")?;
+    let max_transfers = args.value("--max-transfers")?;
+    let edges = ctx.read_edges(args.arg(2))?;
+    let (flow, transfers) =
+        graph::compute_flow(&from, &to, &edges, max_flow, max_hops, max_transfers);
+    if let Some(dotfile) = args.option("--dot") {
+        fs::write(dotfile, graph::transfers_to_dot(&transfers, &ctx.names))
+            .map_err(|e| format!("Error writing \"{dotfile}\": {e}"))?;
+        eprintln!("Wrote dotfile {dotfile}.");
+    }
+    let calldata = (args.flag("--calldata") && !transfers.is_empty())
+        .then(|| abi::to_hex(&abi::encode_transfer_through(&transfers)));
+
+    if ctx.output == OutputFormat::Table {
+        println!(
+            "Found flow: {} ({} CRC, {} TC)",
+            flow.to_decimal(),
+            flow.to_crc(),
+            time_circles::crc_to_tc(flow, timestamp).to_crc()
+        );
+    }
+    let result = FlowResult { flow, transfers }.to_unit(unit, timestamp);
+    let table = transfers_table(&result.transfers, ctx);
+    let output = FlowOutput {
+        max_flow_value: result.flow,
+        transfer_steps: result
+            .transfers
+            .iter()
+            .enumerate()
+            .map(|(step, e)| TransferStep {
+                from: e.from,
+                to: e.to,
+                token: e.token,
+                value: e.capacity,
+                step,
+            })
+            .collect(),
+        calldata,
+    };
+    output::print(ctx.output, &output, &table);
+    if let (OutputFormat::Table, Some(data)) = (ctx.output, &output.calldata) {
+        println!("Calldata for {} on the Hub:", abi::TRANSFER_THROUGH_SIG);
+        println!("{data}");
+        println!("To check, run the following command (requires foundry):");
+        println!(
+            "cast call '{}' '{data}' --rpc-url {RPC_URL} --from {}",
+            abi::HUB_ADDRESS,
+            &output.transfer_steps[0].from
+        );
+    }
+    Ok(())
+}
+
+fn transfers_table(transfers: &[Edge], ctx: &Context) -> Table {
+    let mut table = Table::new(&["from", "to", "token_owner", "amount"]);
+    for transfer in transfers {
+        table.push(vec![
+            ctx.address(&transfer.from),
+            ctx.address(&transfer.to),
+            ctx.address(&transfer.token),
+            ctx.amount(transfer.capacity),
+        ]);
+    }
+    table
+}
+
+fn paths(args: &Args, ctx: &Context) -> CliResult<()> {
+    let (from, to) = (ctx.resolve(args.arg(0))?, ctx.resolve(args.arg(1))?);
+    let k = args.value("--k")?.unwrap_or(5);
+    let cost: PathCost = args.value("--cost")?.unwrap_or_default();
+    let edges = ctx.read_edges(args.arg(2))?;
+    let paths = graph::k_shortest_paths(&from, &to, &edges, k, cost);
+    output::print(ctx.output, &paths, &paths_table(&paths, ctx));
+    Ok(())
+}
+
+fn paths_table(paths: &[TrustPath], ctx: &Context) -> Table {
+    let mut table = Table::new(&["cost", "capacity", "path"]);
+    for path in paths {
+        let addresses = path.addresses().iter().map(|a| ctx.address(a)).collect();
+        table.push(vec![
+            path.cost.to_string(),
+            ctx.amount(path.capacity),
+            join_path(addresses, ctx.output),
+        ]);
+    }
+    table
+}
+
+fn join_path(addresses: Vec<String>, output: OutputFormat) -> String {
+    match output {
+        OutputFormat::Table => addresses.join(" -> "),
+        _ => addresses.join(" "),
+    }
+}
+
+fn explain(args: &Args, ctx: &Context) -> CliResult<()> {
+    let (from, to) = (ctx.resolve(args.arg(0))?, ctx.resolve(args.arg(1))?);
+    let value = amount_option(args, "--value")?.unwrap_or(U256::MAX);
+    let max_hops = args.value("--max-hops")?;
+    let edges = ctx.read_edges(args.arg(2))?;
+    let explanation = graph::explain_flow(&from, &to, &edges, value, max_hops, &ctx.names);
+    let mut table = Table::new(&[
+        "kind",
+        "from",
+        "to",
+        "token_owner",
+        "capacity",
+        "suggestion",
+    ]);
+    for bottleneck in explanation
+        .sender_balance
+        .iter()
+        .chain(&explanation.send_limit)
+        .chain(&explanation.trust_limit)
+    {
+        let kind = match bottleneck.kind {
+            BottleneckKind::SenderBalance => "sender_balance",
+            BottleneckKind::SendLimit => "send_limit",
+            BottleneckKind::TrustLimit => "trust_limit",
+        };
+        let optional = |a: Option<Address>| a.map_or(String::new(), |a| ctx.address(&a));
+        table.push(vec![
+            kind.to_string(),
+            optional(bottleneck.from),
+            optional(bottleneck.to),
+            ctx.address(&bottleneck.token),
+            ctx.amount(bottleneck.capacity),
+            bottleneck.suggestion.clone(),
+        ]);
+    }
+    if ctx.output == OutputFormat::Table {
+        println!("Maximum flow: {} CRC", explanation.max_flow.to_crc());
+        if explanation.max_flow >= explanation.requested_flow {
+            println!("The requested flow can be sent.");
+            return Ok(());
+        }
+        if explanation.distance_limited {
+            println!("The flow is limited by the maximum number of hops.");
+            return Ok(());
+        }
+    }
+    output::print(ctx.output, &explanation, &table);
+    Ok(())
+}
+
+fn matrix(args: &Args, ctx: &Context) -> CliResult<()> {
+    let sources = ctx.resolve_list(args.arg(1))?;
+    let targets = ctx.resolve_list(args.arg(2))?;
+    let max_hops = args.value("--max-hops")?;
+    let edges = ctx.read_edges(args.arg(0))?;
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let matrix = graph::compute_max_flow_matrix(&sources, &targets, &edges, max_hops, threads);
+    let mut table = Table::new(&["from", "to", "flow"]);
+    let mut values = vec![];
+    for (source, flows) in sources.iter().zip(matrix) {
+        for (target, flow) in targets.iter().zip(flows) {
+            table.push(vec![
+                ctx.address(source),
+                ctx.address(target),
+                ctx.amount(flow),
+            ]);
+            values.push(json!({"from": source, "to": target, "flow": flow}));
+        }
+    }
+    output::print(ctx.output, &values, &table);
+    Ok(())
+}
+
+fn cut_tree(args: &Args, ctx: &Context) -> CliResult<()> {
+    let tree_file = args.arg(1);
+    let edges = ctx.read_edges(args.arg(0))?;
+    let tree = UndirectedCutTree::build(&edges);
+    io::write_cut_tree(&tree, tree_file)
+        .map_err(|e| format!("Error writing \"{tree_file}\": {e}"))?;
+    eprintln!(
+        "Wrote cut tree of {} addresses to {tree_file}.",
+        tree.addresses().len()
+    );
+    Ok(())
+}
+
+fn min_cut(args: &Args, ctx: &Context) -> CliResult<()> {
+    let tree_file = args.arg(0);
+    let sources = ctx.resolve_list(args.arg(1))?;
+    let targets = ctx.resolve_list(args.arg(2))?;
+    let tree =
+        io::read_cut_tree(tree_file).map_err(|e| format!("Error loading \"{tree_file}\": {e}"))?;
+    let mut table = Table::new(&["from", "to", "min_cut"]);
+    let mut values = vec![];
+    for source in &sources {
+        for target in targets.iter().filter(|target| *target != source) {
+            let cut = tree.undirected_min_cut(source, target);
+            table.push(vec![
+                ctx.address(source),
+                ctx.address(target),
+                cut.map_or("-".to_string(), |cut| ctx.amount(cut)),
+            ]);
+            values.push(json!({"from": source, "to": target, "min_cut": cut}));
+        }
+    }
+    output::print(ctx.output, &values, &table);
+    Ok(())
+}
+
+fn stats(args: &Args, ctx: &Context) -> CliResult<()> {
+    let top = args.value("--top")?.unwrap_or(10);
+    let edges = ctx.read_edges(args.arg(0))?;
+    let graph = AddressGraph::new(&edges);
+    let stats = analytics::address_stats(&graph, BETWEENNESS_SAMPLES);
+    if ctx.output == OutputFormat::Table {
+        print!("{}", analytics::Report::new(&graph, &stats, top));
+        return Ok(());
+    }
+    let mut table = Table::new(&[
+        "address",
+        "in_degree",
+        "out_degree",
+        "component",
+        "pagerank",
+        "betweenness",
+    ]);
+    for s in &stats {
+        table.push(vec![
+            ctx.address(&s.address),
+            s.in_degree.to_string(),
+            s.out_degree.to_string(),
+            s.component.to_string(),
+            s.pagerank.to_string(),
+            s.betweenness.to_string(),
+        ]);
+    }
+    output::print(ctx.output, &stats, &table);
+    Ok(())
+}
+
+fn liquidity(args: &Args, ctx: &Context) -> CliResult<()> {
+    if ctx.output != OutputFormat::Table {
+        return Err("The liquidity report is only available as table.".into());
+    }
+    let count = args.value("--pairs")?.unwrap_or(DEFAULT_SAMPLE_PAIRS);
+    let path = args.arg(0);
+    let db = io::read_safes_file(path).map_err(|e| format!("Error loading \"{path}\": {e}"))?;
+    let users = db
+        .safes()
+        .iter()
+        .filter(|(_, safe)| !safe.organization)
+        .map(|(address, _)| *address)
+        .collect::<Vec<_>>();
+    let pairs = liquidity::sample_pairs(&users, count);
+    print!("{}", liquidity::LiquidityReport::new(&db, &pairs));
+    Ok(())
+}
+
+fn export(args: &Args, ctx: &Context) -> CliResult<()> {
+    let output = args.arg(2);
+    let format = graph::ExportFormat::from_file_name(output)
+        .ok_or_else(|| format!("Unknown output format for \"{output}\""))?;
+    let center = ctx.resolve(args.arg(1))?;
+    let hops = args.value("--hops")?.unwrap_or(2);
+    let highlight = match args.option("--highlight") {
+        Some(file) => io::read_edges_json(file)
+            .map_err(|e| format!("Error reading \"{file}\": {e}"))?
+            .edges()
+            .clone(),
+        None => vec![],
+    };
+    let edges = ctx.read_edges(args.arg(0))?;
+    let subgraph = graph::neighborhood(&edges, &center, hops);
+    let options = graph::ExportOptions {
+        expand: args.flag("--expand"),
+        highlight,
+        labels: ctx.names.clone(),
+    };
+    fs::write(output, graph::export_graph(&subgraph, format, &options))
+        .map_err(|e| format!("Error writing \"{output}\": {e}"))?;
+    eprintln!("Wrote {} edges to {output}.", subgraph.len());
+    Ok(())
+}
+
+fn verify(args: &Args, ctx: &Context) -> CliResult<()> {
+    let (transfers_file, safes_file) = (args.arg(0), args.arg(1));
+    let result: FlowResult = serde_json::from_str::<FlowInput>(
+        &fs::read_to_string(transfers_file)
+            .map_err(|e| format!("Error reading \"{transfers_file}\": {e}"))?,
+    )
+    .map_err(|e| format!("Invalid flow result in \"{transfers_file}\": {e}"))?
+    .into();
+    let db = io::read_safes_file(safes_file)
+        .map_err(|e| format!("Error loading \"{safes_file}\": {e}"))?;
+    let sender = match args.option("--sender") {
+        Some(sender) => ctx.resolve(sender)?,
+        None => {
+            result
+                .transfers
+                .first()
+                .ok_or("No transfers to verify")?
+                .from
+        }
+    };
+    let summary = HubState::from_db(&db).transfer_through(&sender, &result.transfers)?;
+    if summary.amount != result.flow {
+        return Err(format!(
+            "The transfers move {}, but the flow is {}",
+            summary.amount.to_decimal(),
+            result.flow.to_decimal()
+        )
+        .into());
+    }
+    let mut table = Table::new(&["from", "to", "amount"]);
+    table.push(vec![
+        ctx.address(&summary.from),
+        ctx.address(&summary.to),
+        ctx.amount(summary.amount),
+    ]);
+    if ctx.output == OutputFormat::Table {
+        println!("The transfers are valid.");
+    }
+    let value = json!({"from": summary.from, "to": summary.to, "amount": summary.amount});
+    output::print(ctx.output, &value, &table);
+    Ok(())
+}
+
+fn convert(args: &Args, ctx: &Context) -> CliResult<()> {
+    let (input, output) = (args.arg(0), args.arg(1));
+    let to = match args.option("--to") {
+        Some(to) => to.as_str(),
+        None if output.ends_with(".csv") => "edges-csv",
+        None if output.ends_with(".json") => "edges-json",
+        None if output.ends_with(".dot") => "dot",
+        None => "edges-bin",
+    };
+    let format = io::detect_format(input).map_err(|e| format!("Error reading \"{input}\": {e}"))?;
+    let written = if to == "safes-bin" {
+        if !format.contains_safes() {
+            return Err(format!("\"{input}\" does not contain safes").into());
+        }
+        let db = io::read_safes_file(input)?;
+        io::write_safes_binary(&db, output)?;
+        format!("{} safes", db.safes().len())
+    } else {
+        let edges = ctx.read_edges(input)?;
+        match to {
+            "edges-bin" => io::write_edges_binary(&edges, output)?,
+            "edges-csv" => io::write_edges_csv(&edges, output)?,
+            "edges-json" => io::write_edges_json(&edges, output)?,
+            "dot" => fs::write(output, graph::transfers_to_dot(edges.edges(), &ctx.names))?,
+            _ => return Err(format!("Unknown output format \"{to}\"").into()),
+        }
+        format!("{} edges", edges.edge_count())
+    };
+    eprintln!("Wrote {written} to {output}.");
+    Ok(())
+}
+
+fn diff(args: &Args, ctx: &Context) -> CliResult<()> {
+    let old = ctx.read_edges(args.arg(0))?;
+    let new = ctx.read_edges(args.arg(1))?;
+    let changes = edge::diff_edges(&old, &new);
+    let mut table = Table::new(&[
+        "change",
+        "from",
+        "to",
+        "token_owner",
+        "old_capacity",
+        "new_capacity",
+    ]);
+    let optional = |amount: Option<U256>| amount.map_or(String::new(), |a| ctx.amount(a));
+    for change in &changes {
+        let kind = match (change.old_capacity, change.new_capacity) {
+            (None, _) => "added",
+            (_, None) => "removed",
+            _ => "changed",
+        };
+        table.push(vec![
+            kind.to_string(),
+            ctx.address(&change.from),
+            ctx.address(&change.to),
+            ctx.address(&change.token),
+            optional(change.old_capacity),
+            optional(change.new_capacity),
+        ]);
+    }
+    output::print(ctx.output, &changes, &table);
+    Ok(())
+}
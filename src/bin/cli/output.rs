@@ -0,0 +1,99 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    #[default]
+    Table,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err("expected json, table or csv".to_string()),
+        }
+    }
+}
+
+/// Rows of a result that can be printed as an aligned table or as CSV.
+pub struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&'static str]) -> Table {
+        Table {
+            header: header.to_vec(),
+            rows: vec![],
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<String>) {
+        assert_eq!(row.len(), self.header.len());
+        self.rows.push(row);
+    }
+
+    pub fn to_csv(&self) -> String {
+        let line = |fields: Vec<String>| {
+            fields
+                .iter()
+                .map(|f| escape_csv(f))
+                .collect::<Vec<_>>()
+                .join(",")
+                + "\n"
+        };
+        let header = self.header.iter().map(|h| h.to_string()).collect();
+        line(header) + &self.rows.iter().cloned().map(line).collect::<String>()
+    }
+}
+
+/// Aligned columns, the last column is not padded.
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let mut widths = self.header.iter().map(|h| h.len()).collect::<Vec<_>>();
+        for row in &self.rows {
+            for (width, field) in widths.iter_mut().zip(row) {
+                *width = (*width).max(field.chars().count());
+            }
+        }
+        let header = self.header.iter().map(|h| h.to_string()).collect();
+        for row in std::iter::once(&header).chain(&self.rows) {
+            let mut line = String::new();
+            for (i, (field, width)) in row.iter().zip(&widths).enumerate() {
+                if i + 1 == row.len() {
+                    line += field;
+                } else {
+                    line += &format!("{field:width$}  ");
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Prints `value` as JSON or `table` as table or CSV, depending on `format`.
+pub fn print(format: OutputFormat, value: &impl Serialize, table: &Table) {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value).unwrap()),
+        OutputFormat::Table => print!("{table}"),
+        OutputFormat::Csv => print!("{}", table.to_csv()),
+    }
+}
@@ -0,0 +1,221 @@
+//! Interactive mode that keeps the edges in memory between commands, so that
+//! changes to capacities can be explored without reloading the edges.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use pathfinder2::graph;
+use pathfinder2::types::edge::{eq_up_to_capacity, EdgeDB};
+use pathfinder2::types::{Edge, U256};
+
+use super::output::Table;
+use super::{paths_table, transfers_table, CliResult, Context};
+
+const HELP: &str = "Commands:
+    flow <from> <to> [<max_hops>] [<max_flow>]  compute the maximum flow and its transfers
+    paths <from> <to> [<k>]                     list the k (default 5) shortest paths
+    neighbors <address>                         list the incoming and outgoing edges
+    set <from> <to> <token_owner> <capacity>    set the capacity of an edge
+    undo                                        revert the last set
+    dot <dotfile>                               write the transfers of the last flow as dot
+    history                                     list the previous commands, !<n> repeats one
+    quit";
+
+struct Session<'a> {
+    edges: EdgeDB,
+    ctx: &'a Context,
+    /// The edges as they were before each `set`, with a capacity of zero for added edges.
+    undo: Vec<Edge>,
+    /// Transfers of the last flow.
+    transfers: Vec<Edge>,
+    history: Vec<String>,
+}
+
+/// Reads commands from stdin until `quit` or the end of the input.
+pub fn run(edges: EdgeDB, ctx: &Context) -> CliResult<()> {
+    let mut session = Session {
+        edges,
+        ctx,
+        undo: vec![],
+        transfers: vec![],
+        history: vec![],
+    };
+    println!("{HELP}");
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let mut line = line?.trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(n) = line.strip_prefix('!') {
+            let previous = n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| session.history.get(n.wrapping_sub(1)));
+            let Some(previous) = previous else {
+                eprintln!("Error: No command {n} in the history");
+                continue;
+            };
+            line = previous.clone();
+            println!("{line}");
+        }
+        session.history.push(line.clone());
+        match session.execute(&line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("Error: {e}"),
+        }
+    }
+    Ok(())
+}
+
+impl Session<'_> {
+    /// Executes one command and returns false if the session should end.
+    fn execute(&mut self, line: &str) -> CliResult<bool> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["quit" | "exit"] => return Ok(false),
+            ["help"] => println!("{HELP}"),
+            ["flow", from, to, rest @ ..] if rest.len() <= 2 => {
+                self.flow(from, to, rest.first(), rest.get(1))?
+            }
+            ["paths", from, to, rest @ ..] if rest.len() <= 1 => {
+                self.paths(from, to, rest.first())?
+            }
+            ["neighbors", address] => self.neighbors(address)?,
+            ["set", from, to, token, capacity] => self.set(from, to, token, capacity)?,
+            ["undo"] => self.undo()?,
+            ["dot", file] => {
+                fs::write(
+                    file,
+                    graph::transfers_to_dot(&self.transfers, &self.ctx.names),
+                )?;
+                println!("Wrote {} transfers to {file}.", self.transfers.len());
+            }
+            ["history"] => {
+                for (i, line) in self.history.iter().enumerate() {
+                    println!("{:>4}  {line}", i + 1);
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Invalid command \"{line}\", type help for a list of commands."
+                )
+                .into())
+            }
+        }
+        Ok(true)
+    }
+
+    fn flow(
+        &mut self,
+        from: &str,
+        to: &str,
+        max_hops: Option<&&str>,
+        max_flow: Option<&&str>,
+    ) -> CliResult<()> {
+        let (from, to) = (self.ctx.resolve(from)?, self.ctx.resolve(to)?);
+        let max_hops = max_hops
+            .map(|hops| {
+                hops.parse()
+                    .map_err(|_| format!("Invalid number of hops \"{hops}\""))
+            })
+            .transpose()?;
+        let max_flow = max_flow
+            .map(|amount| U256::parse_amount(amount))
+            .transpose()?
+            .unwrap_or(U256::MAX);
+        let (flow, transfers) =
+            graph::compute_flow(&from, &to, &self.edges, max_flow, max_hops, None);
+        println!("Found flow: {} CRC", flow.to_crc());
+        print!("{}", transfers_table(&transfers, self.ctx));
+        self.transfers = transfers;
+        Ok(())
+    }
+
+    fn paths(&self, from: &str, to: &str, k: Option<&&str>) -> CliResult<()> {
+        let (from, to) = (self.ctx.resolve(from)?, self.ctx.resolve(to)?);
+        let k = k
+            .map(|k| {
+                k.parse()
+                    .map_err(|_| format!("Invalid number of paths \"{k}\""))
+            })
+            .transpose()?
+            .unwrap_or(5);
+        let paths = graph::k_shortest_paths(&from, &to, &self.edges, k, Default::default());
+        print!("{}", paths_table(&paths, self.ctx));
+        Ok(())
+    }
+
+    fn neighbors(&self, address: &str) -> CliResult<()> {
+        let address = self.ctx.resolve(address)?;
+        let mut table = Table::new(&["direction", "address", "token_owner", "capacity"]);
+        for (direction, edges) in [
+            ("in", self.edges.incoming(&address)),
+            ("out", self.edges.outgoing(&address)),
+        ] {
+            for edge in edges {
+                let other = if direction == "in" {
+                    edge.from
+                } else {
+                    edge.to
+                };
+                table.push(vec![
+                    direction.to_string(),
+                    self.ctx.address(&other),
+                    self.ctx.address(&edge.token),
+                    self.ctx.amount(edge.capacity),
+                ]);
+            }
+        }
+        print!("{table}");
+        Ok(())
+    }
+
+    fn set(&mut self, from: &str, to: &str, token: &str, capacity: &str) -> CliResult<()> {
+        let edge = Edge {
+            from: self.ctx.resolve(from)?,
+            to: self.ctx.resolve(to)?,
+            token: self.ctx.resolve(token)?,
+            capacity: U256::parse_amount(capacity)?,
+        };
+        let previous = self.capacity(&edge);
+        self.undo.push(Edge {
+            capacity: previous,
+            ..edge
+        });
+        self.edges.update(edge);
+        println!(
+            "Changed capacity from {} CRC to {} CRC.",
+            previous.to_crc(),
+            edge.capacity.to_crc()
+        );
+        Ok(())
+    }
+
+    fn undo(&mut self) -> CliResult<()> {
+        let edge = self.undo.pop().ok_or("Nothing to undo.")?;
+        let changed = self.capacity(&edge);
+        self.edges.update(edge);
+        println!(
+            "Changed capacity from {} CRC back to {} CRC.",
+            changed.to_crc(),
+            edge.capacity.to_crc()
+        );
+        Ok(())
+    }
+
+    /// Returns the current capacity of the edge with the same endpoints and token as `edge`.
+    fn capacity(&self, edge: &Edge) -> U256 {
+        self.edges
+            .outgoing(&edge.from)
+            .into_iter()
+            .find(|e| eq_up_to_capacity(e, edge))
+            .map_or(U256::from(0), |e| e.capacity)
+    }
+}
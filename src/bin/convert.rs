@@ -1,59 +1,88 @@
 use std::env;
+use std::fs;
 
+use pathfinder2::graph;
 use pathfinder2::io::*;
 use pathfinder2::safe_db::safes_json::import_from_safes_json;
 
 fn main() {
-    let input_format = env::args().nth(1).and_then(|op| {
+    let mut args = env::args().collect::<Vec<_>>();
+    let names = args.iter().position(|a| a == "--names").map(|i| {
+        let file = args
+            .get(i + 1)
+            .unwrap_or_else(|| panic!("Missing value for --names"))
+            .clone();
+        args.drain(i..i + 2);
+        read_address_book(&file)
+            .unwrap_or_else(|e| panic!("Error reading names from \"{file}\": {e}"))
+    });
+    let input_format = args.get(1).cloned().and_then(|op| {
         if matches!(
             op.as_str(),
-            "--safes-json" | "--safes-bin" | "--edges-csv" | "--edges-bin"
+            "--safes-json" | "--safes-bin" | "--edges-csv" | "--edges-bin" | "--edges-json"
         ) {
             Some(op)
         } else {
             None
         }
     });
-    let output_format = env::args().nth(3).and_then(|op| {
-        if matches!(op.as_str(), "--edges-csv" | "--edges-bin") {
+    let output_format = args.get(3).cloned().and_then(|op| {
+        if matches!(
+            op.as_str(),
+            "--edges-csv" | "--edges-bin" | "--edges-json" | "--dot"
+        ) {
             Some(op)
         } else {
             None
         }
     });
-    if env::args().len() != 5 || input_format.is_none() || output_format.is_none() {
-        println!("Usage: convert <input> <input_file> <output> <output_file>");
+    if args.len() != 5 || input_format.is_none() || output_format.is_none() {
+        println!(
+            "Usage: convert <input> <input_file> <output> <output_file> [--names <names.csv>]"
+        );
         println!("  Where <input> is one of:");
         println!("    --safes-json");
         println!("    --safes-bin");
         println!("    --edges-csv");
         println!("    --edges-bin");
+        println!("    --edges-json");
         println!("  and <output>is one of:");
         println!("    --edges-csv");
         println!("    --edges-bin");
+        println!("    --edges-json");
+        println!("    --dot");
+        println!("  Option --names reads names of addresses (lines of the form address,name)");
+        println!("  that are used as labels in the dot output.");
         return;
     }
 
-    let input_file = env::args().nth(2).unwrap();
+    let input_file = &args[2];
     let edges = match input_format.unwrap().as_str() {
         "--safes-json" => {
-            let safes = import_from_safes_json(&input_file);
+            let safes = import_from_safes_json(input_file);
             safes.edges().clone()
         }
         "--safes-bin" => {
-            let safes = import_from_safes_binary(&input_file).unwrap();
+            let safes = import_from_safes_binary(input_file).unwrap();
             safes.edges().clone()
         }
-        "--edges-csv" => read_edges_csv(&input_file).unwrap(),
-        "--edges-bin" => read_edges_binary(&input_file).unwrap(),
+        "--edges-csv" => read_edges_csv(input_file).unwrap(),
+        "--edges-bin" => read_edges_binary(input_file).unwrap(),
+        "--edges-json" => read_edges_json(input_file).unwrap(),
         _ => unreachable!(),
     };
     println!("Imported {} edges.", edges.edge_count());
 
-    let output_file = env::args().nth(4).unwrap();
+    let output_file = &args[4];
     match output_format.unwrap().as_str() {
-        "--edges-csv" => write_edges_csv(&edges, &output_file).unwrap(),
-        "--edges-bin" => write_edges_binary(&edges, &output_file).unwrap(),
+        "--edges-csv" => write_edges_csv(&edges, output_file).unwrap(),
+        "--edges-bin" => write_edges_binary(&edges, output_file).unwrap(),
+        "--edges-json" => write_edges_json(&edges, output_file).unwrap(),
+        "--dot" => fs::write(
+            output_file,
+            graph::transfers_to_dot(edges.edges(), &names.unwrap_or_default()),
+        )
+        .unwrap(),
         _ => unreachable!(),
     }
     println!("Export done.");
@@ -1,10 +1,78 @@
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
+use log::LevelFilter;
+use pathfinder2::io::read_address_book;
 use pathfinder2::server;
+use pathfinder2::server::logging::JsonLogger;
+use pathfinder2::server::rate_limit::{read_api_keys, RateLimitConfig};
+use pathfinder2::server::ServerConfig;
 
 fn main() {
-    let listen_at = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
-    server::start_server(&listen_at, 10, 4);
+    let mut listen_at = "127.0.0.1:8080".to_string();
+    let mut config = ServerConfig::default();
+    let mut log_level = LevelFilter::Info;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--queue-size" => config.queue_size = parse_value(&arg, args.next()),
+            "--threads" => config.threads = parse_value(&arg, args.next()),
+            "--cache-size" => config.cache_size = parse_value(&arg, args.next()),
+            "--snapshot" => config.snapshot_path = Some(parse_value(&arg, args.next())),
+            "--snapshot-interval" => {
+                config.snapshot_interval = Duration::from_secs(parse_value(&arg, args.next()))
+            }
+            "--log-level" => log_level = parse_value(&arg, args.next()),
+            "--names" => {
+                let file: String = parse_value(&arg, args.next());
+                config.names = read_address_book(&file)
+                    .unwrap_or_else(|e| panic!("Error reading names from \"{file}\": {e}"))
+            }
+            "--rate-limit-budget" => {
+                config
+                    .rate_limit
+                    .get_or_insert_with(RateLimitConfig::default)
+                    .budget = parse_value(&arg, args.next())
+            }
+            "--rate-limit-refill" => {
+                config
+                    .rate_limit
+                    .get_or_insert_with(RateLimitConfig::default)
+                    .refill_per_second = parse_value(&arg, args.next())
+            }
+            "--rate-limit-cost" => {
+                config
+                    .rate_limit
+                    .get_or_insert_with(RateLimitConfig::default)
+                    .cost = parse_value(&arg, args.next())
+            }
+            "--api-keys" => {
+                let file: String = parse_value(&arg, args.next());
+                config
+                    .rate_limit
+                    .get_or_insert_with(RateLimitConfig::default)
+                    .api_keys = read_api_keys(&file)
+                    .unwrap_or_else(|e| panic!("Error reading API keys from \"{file}\": {e}"))
+            }
+            _ if arg.starts_with("--") => {
+                println!("Usage: server [<ip-address>:<port>] [--queue-size <n>] [--threads <n>] [--cache-size <n>] [--snapshot <file>] [--snapshot-interval <seconds>] [--log-level <level>] [--names <names.csv>] [--rate-limit-budget <cost>] [--rate-limit-refill <cost per second>] [--rate-limit-cost <name=value,...>] [--api-keys <file>]");
+                return;
+            }
+            _ => listen_at = arg,
+        }
+    }
+    JsonLogger::init(log_level).expect("Could not initialize logging.");
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let s = shutdown.clone();
+    ctrlc::set_handler(move || s.store(true, Ordering::Relaxed))
+        .expect("Could not install signal handler.");
+    server::start_server(&listen_at, config, shutdown);
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("Invalid or missing value for {option}."))
 }
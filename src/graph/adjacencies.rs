@@ -1,13 +1,13 @@
 use crate::graph::Node;
 use crate::types::edge::EdgeDB;
-use crate::types::{Edge, U256};
+use crate::types::{Edge, I256, U256};
 use std::cmp::{max, Reverse};
 use std::collections::HashMap;
 
 pub struct Adjacencies<'a> {
     edges: &'a EdgeDB,
     lazy_adjacencies: HashMap<Node, HashMap<Node, U256>>,
-    capacity_adjustments: HashMap<Node, HashMap<Node, U256>>,
+    capacity_adjustments: HashMap<Node, HashMap<Node, I256>>,
 }
 
 // fn pseudo_node(edge: Edge) -> Node {
@@ -38,8 +38,14 @@ impl<'a> Adjacencies<'a> {
         }
     }
 
+    /// Returns the edges with positive residual capacity, i.e. the capacity
+    /// of the edge plus all adjustments.
     pub fn outgoing_edges_sorted_by_capacity(&mut self, from: &Node) -> Vec<(Node, U256)> {
-        let mut adjacencies = self.adjacencies_from(from);
+        let mut adjacencies = self
+            .adjacencies_from(from)
+            .into_iter()
+            .map(|(node, capacity)| (node, I256::from(capacity)))
+            .collect::<HashMap<_, _>>();
         if let Some(adjustments) = self.capacity_adjustments.get(from) {
             for (node, c) in adjustments {
                 *adjacencies.entry(node.clone()).or_default() += *c;
@@ -47,13 +53,25 @@ impl<'a> Adjacencies<'a> {
         }
         let mut result = adjacencies
             .into_iter()
+            .map(|(node, capacity)| {
+                let capacity = capacity.to_u256().unwrap_or_else(|| {
+                    panic!("Negative residual capacity {capacity} from {from} to {node}")
+                });
+                (node, capacity)
+            })
             .filter(|(_, cap)| *cap != U256::from(0))
             .collect::<Vec<(Node, U256)>>();
         result.sort_unstable_by_key(|(addr, capacity)| (Reverse(*capacity), addr.clone()));
         result
     }
 
-    pub fn adjust_capacity(&mut self, from: &Node, to: &Node, adjustment: U256) {
+    /// Removes all capacity adjustments, so that the cached adjacencies can be
+    /// reused for another flow computation on the same edges.
+    pub fn reset_adjustments(&mut self) {
+        self.capacity_adjustments.clear();
+    }
+
+    pub fn adjust_capacity(&mut self, from: &Node, to: &Node, adjustment: I256) {
         *self
             .capacity_adjustments
             .entry(from.clone())
@@ -72,7 +90,8 @@ impl<'a> Adjacencies<'a> {
         }
     }
 
-    fn adjacencies_from(&mut self, from: &Node) -> HashMap<Node, U256> {
+    /// Returns the original capacities of the edges starting at `from`, without adjustments.
+    pub fn adjacencies_from(&mut self, from: &Node) -> HashMap<Node, U256> {
         self.lazy_adjacencies
             .entry(from.clone())
             .or_insert_with(|| {
@@ -109,7 +128,7 @@ impl<'a> Adjacencies<'a> {
                         for edge in self.edges.incoming(to) {
                             if edge.token == *token {
                                 if is_return_to_owner {
-                                    capacity += edge.capacity
+                                    capacity = capacity.saturating_add(edge.capacity)
                                 } else {
                                     capacity = max(capacity, edge.capacity)
                                 }
@@ -123,3 +142,60 @@ impl<'a> Adjacencies<'a> {
             .clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Address;
+
+    fn edges() -> (Address, Address, EdgeDB) {
+        let a = Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E");
+        let b = Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37");
+        let edges = EdgeDB::new(vec![Edge {
+            from: a,
+            to: b,
+            token: a,
+            capacity: U256::from(10),
+        }]);
+        (a, b, edges)
+    }
+
+    #[test]
+    fn residual_capacity() {
+        let (a, _, edges) = edges();
+        let mut adjacencies = Adjacencies::new(&edges);
+        let (from, balance) = (Node::Node(a), Node::BalanceNode(a, a));
+        let flow = I256::from(U256::from(4));
+        adjacencies.adjust_capacity(&from, &balance, -flow);
+        adjacencies.adjust_capacity(&balance, &from, flow);
+        assert_eq!(
+            adjacencies.outgoing_edges_sorted_by_capacity(&from),
+            vec![(balance.clone(), U256::from(6))]
+        );
+        assert_eq!(
+            adjacencies.outgoing_edges_sorted_by_capacity(&balance),
+            vec![
+                (Node::TrustNode(edges.edges()[0].to, a), U256::from(10)),
+                (from.clone(), U256::from(4))
+            ]
+        );
+        // Saturating the edge removes it, cancelling the flow restores it.
+        adjacencies.adjust_capacity(&from, &balance, -I256::from(U256::from(6)));
+        assert_eq!(adjacencies.outgoing_edges_sorted_by_capacity(&from), vec![]);
+        adjacencies.adjust_capacity(&from, &balance, I256::from(U256::from(10)));
+        assert_eq!(
+            adjacencies.outgoing_edges_sorted_by_capacity(&from),
+            vec![(balance, U256::from(10))]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Negative residual capacity")]
+    fn negative_residual_capacity() {
+        let (a, _, edges) = edges();
+        let mut adjacencies = Adjacencies::new(&edges);
+        let (from, balance) = (Node::Node(a), Node::BalanceNode(a, a));
+        adjacencies.adjust_capacity(&from, &balance, -I256::from(U256::from(11)));
+        adjacencies.outgoing_edges_sorted_by_capacity(&from);
+    }
+}
@@ -0,0 +1,403 @@
+//! Structural statistics of the trust network on the level of addresses.
+//!
+//! An edge `from -> to` of the `EdgeDB` means that `from` can send tokens to `to`, i.e. for
+//! trust edges that `to` trusts `from`. Edges with the same endpoints but different tokens
+//! are merged and their capacities added up.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use crate::types::edge::EdgeDB;
+use crate::types::Address;
+
+pub const DEFAULT_DAMPING: f64 = 0.85;
+pub const DEFAULT_PAGERANK_ITERATIONS: usize = 50;
+
+/// The address-level graph derived from an `EdgeDB`.
+pub struct AddressGraph {
+    addresses: Vec<Address>,
+    /// Outgoing connections with their total capacity.
+    outgoing: Vec<Vec<(usize, f64)>>,
+    incoming: Vec<Vec<(usize, f64)>>,
+}
+
+impl AddressGraph {
+    pub fn new(edges: &EdgeDB) -> AddressGraph {
+        let mut connections: BTreeMap<(Address, Address), f64> = BTreeMap::new();
+        for edge in edges.edges() {
+            if edge.from != edge.to && edge.capacity != 0.into() {
+                *connections.entry((edge.from, edge.to)).or_default() += edge.capacity.to_f64();
+            }
+        }
+        let mut index: BTreeMap<Address, usize> = connections
+            .keys()
+            .flat_map(|(from, to)| [(*from, 0), (*to, 0)])
+            .collect();
+        for (i, v) in index.values_mut().enumerate() {
+            *v = i;
+        }
+        let mut outgoing = vec![vec![]; index.len()];
+        let mut incoming = vec![vec![]; index.len()];
+        for ((from, to), capacity) in connections {
+            outgoing[index[&from]].push((index[&to], capacity));
+            incoming[index[&to]].push((index[&from], capacity));
+        }
+        AddressGraph {
+            addresses: index.into_keys().collect(),
+            outgoing,
+            incoming,
+        }
+    }
+
+    pub fn addresses(&self) -> &Vec<Address> {
+        &self.addresses
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.outgoing.iter().map(Vec::len).sum()
+    }
+
+    pub fn in_degree(&self, node: usize) -> usize {
+        self.incoming[node].len()
+    }
+
+    pub fn out_degree(&self, node: usize) -> usize {
+        self.outgoing[node].len()
+    }
+
+    /// Returns a map from degree to the number of addresses with that in-degree.
+    pub fn in_degree_distribution(&self) -> BTreeMap<usize, usize> {
+        distribution(self.incoming.iter().map(Vec::len))
+    }
+
+    /// Returns a map from degree to the number of addresses with that out-degree.
+    pub fn out_degree_distribution(&self) -> BTreeMap<usize, usize> {
+        distribution(self.outgoing.iter().map(Vec::len))
+    }
+
+    /// Computes the strongly connected components (Tarjan's algorithm).
+    /// Returns the component index of each address, components are numbered
+    /// by decreasing size.
+    pub fn strongly_connected_components(&self) -> Vec<usize> {
+        const UNVISITED: usize = usize::MAX;
+        let n = self.addresses.len();
+        let mut order = vec![UNVISITED; n];
+        let mut low_link = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = vec![];
+        let mut component = vec![UNVISITED; n];
+        let mut components = 0;
+        let mut counter = 0;
+        for root in 0..n {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            // Explicit call stack of (node, index of the next outgoing edge).
+            let mut call_stack = vec![(root, 0)];
+            order[root] = counter;
+            low_link[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            while let Some((node, next)) = call_stack.last_mut() {
+                let node = *node;
+                if let Some((target, _)) = self.outgoing[node].get(*next) {
+                    let target = *target;
+                    *next += 1;
+                    if order[target] == UNVISITED {
+                        order[target] = counter;
+                        low_link[target] = counter;
+                        counter += 1;
+                        stack.push(target);
+                        on_stack[target] = true;
+                        call_stack.push((target, 0));
+                    } else if on_stack[target] {
+                        low_link[node] = low_link[node].min(order[target]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some((parent, _)) = call_stack.last() {
+                    low_link[*parent] = low_link[*parent].min(low_link[node]);
+                }
+                if low_link[node] == order[node] {
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component[member] = components;
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components += 1;
+                }
+            }
+        }
+
+        let mut sizes = vec![0usize; components];
+        for c in &component {
+            sizes[*c] += 1;
+        }
+        let mut by_size = (0..components).collect::<Vec<_>>();
+        by_size.sort_by_key(|c| (std::cmp::Reverse(sizes[*c]), *c));
+        let mut renumbering = vec![0; components];
+        for (new, old) in by_size.into_iter().enumerate() {
+            renumbering[old] = new;
+        }
+        component.into_iter().map(|c| renumbering[c]).collect()
+    }
+
+    /// Computes the PageRank of each address, where rank is passed from the trusting
+    /// to the trusted address (i.e. against the direction of the edges), in proportion
+    /// to the capacity of the edges.
+    pub fn pagerank(&self, damping: f64, iterations: usize) -> Vec<f64> {
+        let n = self.addresses.len();
+        if n == 0 {
+            return vec![];
+        }
+        let total_incoming = self
+            .incoming
+            .iter()
+            .map(|inc| inc.iter().map(|(_, c)| c).sum::<f64>())
+            .collect::<Vec<_>>();
+        let mut rank = vec![1.0 / n as f64; n];
+        for _ in 0..iterations {
+            // Rank of addresses that do not trust anyone is distributed evenly.
+            let dangling = (0..n)
+                .filter(|node| total_incoming[*node] == 0.0)
+                .map(|node| rank[node])
+                .sum::<f64>();
+            let mut next = vec![(1.0 - damping + damping * dangling) / n as f64; n];
+            for (truster, trusted) in self.incoming.iter().enumerate() {
+                for (node, capacity) in trusted {
+                    next[*node] += damping * rank[truster] * capacity / total_incoming[truster];
+                }
+            }
+            rank = next;
+        }
+        rank
+    }
+
+    /// Estimates the betweenness centrality of each address (the number of shortest
+    /// paths between other addresses that pass through it) using Brandes' algorithm
+    /// from `samples` evenly spread source addresses, scaled up to all sources.
+    pub fn betweenness(&self, samples: usize) -> Vec<f64> {
+        let n = self.addresses.len();
+        let mut betweenness = vec![0.0; n];
+        if n == 0 || samples == 0 {
+            return betweenness;
+        }
+        let samples = samples.min(n);
+        for sample in 0..samples {
+            let source = sample * n / samples;
+            let mut distance = vec![usize::MAX; n];
+            let mut path_count = vec![0.0f64; n];
+            let mut predecessors: Vec<Vec<usize>> = vec![vec![]; n];
+            let mut visited = vec![];
+            distance[source] = 0;
+            path_count[source] = 1.0;
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                visited.push(node);
+                for (target, _) in &self.outgoing[node] {
+                    if distance[*target] == usize::MAX {
+                        distance[*target] = distance[node] + 1;
+                        queue.push_back(*target);
+                    }
+                    if distance[*target] == distance[node] + 1 {
+                        path_count[*target] += path_count[node];
+                        predecessors[*target].push(node);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0; n];
+            for node in visited.into_iter().rev() {
+                for pred in &predecessors[node] {
+                    dependency[*pred] +=
+                        path_count[*pred] / path_count[node] * (1.0 + dependency[node]);
+                }
+                if node != source {
+                    betweenness[node] += dependency[node];
+                }
+            }
+        }
+        let scale = n as f64 / samples as f64;
+        betweenness.iter_mut().for_each(|b| *b *= scale);
+        betweenness
+    }
+}
+
+fn distribution(degrees: impl Iterator<Item = usize>) -> BTreeMap<usize, usize> {
+    let mut result = BTreeMap::new();
+    for degree in degrees {
+        *result.entry(degree).or_default() += 1;
+    }
+    result
+}
+
+/// Statistics of a single address.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AddressStats {
+    pub address: Address,
+    pub in_degree: usize,
+    pub out_degree: usize,
+    /// Index of the strongly connected component, 0 is the largest.
+    pub component: usize,
+    pub pagerank: f64,
+    pub betweenness: f64,
+}
+
+/// Computes the statistics of all addresses, estimating the betweenness from
+/// `betweenness_samples` sources.
+pub fn address_stats(graph: &AddressGraph, betweenness_samples: usize) -> Vec<AddressStats> {
+    let components = graph.strongly_connected_components();
+    let pagerank = graph.pagerank(DEFAULT_DAMPING, DEFAULT_PAGERANK_ITERATIONS);
+    let betweenness = graph.betweenness(betweenness_samples);
+    graph
+        .addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| AddressStats {
+            address: *address,
+            in_degree: graph.in_degree(i),
+            out_degree: graph.out_degree(i),
+            component: components[i],
+            pagerank: pagerank[i],
+            betweenness: betweenness[i],
+        })
+        .collect()
+}
+
+/// Summary of the structure of the trust network.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub addresses: usize,
+    pub connections: usize,
+    /// Sizes of the strongly connected components, largest first.
+    pub component_sizes: Vec<usize>,
+    pub in_degrees: BTreeMap<usize, usize>,
+    pub out_degrees: BTreeMap<usize, usize>,
+    /// Addresses with the highest PageRank.
+    pub top_pagerank: Vec<(Address, f64)>,
+    /// Addresses with the highest betweenness.
+    pub top_betweenness: Vec<(Address, f64)>,
+}
+
+impl Report {
+    pub fn new(graph: &AddressGraph, stats: &[AddressStats], top: usize) -> Report {
+        let mut component_sizes: HashMap<usize, usize> = HashMap::new();
+        for s in stats {
+            *component_sizes.entry(s.component).or_default() += 1;
+        }
+        let mut component_sizes = component_sizes.into_values().collect::<Vec<_>>();
+        component_sizes.sort_by(|a, b| b.cmp(a));
+        let top_by = |key: fn(&AddressStats) -> f64| {
+            let mut values = stats
+                .iter()
+                .map(|s| (s.address, key(s)))
+                .collect::<Vec<_>>();
+            values.sort_by(|(a1, v1), (a2, v2)| v2.total_cmp(v1).then(a1.cmp(a2)));
+            values.truncate(top);
+            values
+        };
+        Report {
+            addresses: stats.len(),
+            connections: graph.connection_count(),
+            component_sizes,
+            in_degrees: graph.in_degree_distribution(),
+            out_degrees: graph.out_degree_distribution(),
+            top_pagerank: top_by(|s| s.pagerank),
+            top_betweenness: top_by(|s| s.betweenness),
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(f, "Addresses: {}", self.addresses)?;
+        writeln!(f, "Connections: {}", self.connections)?;
+        writeln!(
+            f,
+            "Strongly connected components: {} (largest: {}, single addresses: {})",
+            self.component_sizes.len(),
+            self.component_sizes.first().unwrap_or(&0),
+            self.component_sizes.iter().filter(|s| **s == 1).count()
+        )?;
+        for (name, degrees) in [("In", &self.in_degrees), ("Out", &self.out_degrees)] {
+            writeln!(f, "{name}-degree distribution (degree: addresses):")?;
+            for (degree, count) in degrees {
+                writeln!(f, "    {degree}: {count}")?;
+            }
+        }
+        writeln!(f, "Top PageRank:")?;
+        for (address, rank) in &self.top_pagerank {
+            writeln!(f, "    {address} {rank:.6}")?;
+        }
+        writeln!(f, "Top betweenness:")?;
+        for (address, betweenness) in &self.top_betweenness {
+            writeln!(f, "    {address} {betweenness:.1}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Edge, U256};
+
+    fn graph() -> (Vec<Address>, AddressGraph) {
+        let addresses = [1, 2, 3, 4, 5].map(|i| Address::from([i; 20])).to_vec();
+        let [a, b, c, d, e] = addresses[..] else {
+            panic!()
+        };
+        let edge = |from, to, capacity: u128| Edge {
+            from,
+            to,
+            token: from,
+            capacity: U256::from(capacity),
+        };
+        // Cycle a -> b -> c -> a, c -> d -> e.
+        let edges = EdgeDB::new(vec![
+            edge(a, b, 10),
+            edge(b, c, 10),
+            edge(c, a, 10),
+            edge(c, d, 10),
+            edge(d, e, 10),
+            edge(d, e, 0),
+        ]);
+        (addresses, AddressGraph::new(&edges))
+    }
+
+    #[test]
+    fn components_and_degrees() {
+        let (addresses, graph) = graph();
+        assert_eq!(graph.addresses(), &addresses);
+        assert_eq!(graph.connection_count(), 5);
+        assert_eq!(graph.strongly_connected_components()[..3], [0, 0, 0]);
+        let components = graph.strongly_connected_components();
+        assert!(components[3] != components[4] && components[3] > 0 && components[4] > 0);
+        assert_eq!(
+            graph.out_degree_distribution(),
+            BTreeMap::from([(0, 1), (1, 3), (2, 1)])
+        );
+        assert_eq!(graph.in_degree_distribution(), BTreeMap::from([(1, 5)]));
+    }
+
+    #[test]
+    fn centrality() {
+        let (_, graph) = graph();
+        let rank = graph.pagerank(DEFAULT_DAMPING, DEFAULT_PAGERANK_ITERATIONS);
+        assert!((rank.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        // Rank is passed from e to d to c.
+        assert!(rank[2] > rank[4]);
+        let betweenness = graph.betweenness(5);
+        // c lies on all paths from a and b to d and e.
+        assert_eq!(betweenness, vec![1.0, 3.0, 5.0, 3.0, 0.0]);
+        let stats = address_stats(&graph, 5);
+        let report = Report::new(&graph, &stats, 1);
+        assert_eq!(report.component_sizes, vec![3, 1, 1]);
+        assert_eq!(report.top_betweenness, vec![(stats[2].address, 5.0)]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::thread;
+
+use crate::graph::adjacencies::Adjacencies;
+use crate::graph::flow::max_flow;
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, U256};
+
+/// Computes the maximum flow value (without transfers) for each pair of source and sink,
+/// using up to `threads` threads. Each thread reuses its cache of adjacencies for all
+/// the pairs it processes. The result is in the same order as `pairs`.
+pub fn compute_max_flows(
+    pairs: &[(Address, Address)],
+    edges: &EdgeDB,
+    max_distance: Option<u64>,
+    threads: usize,
+) -> Vec<U256> {
+    if pairs.is_empty() {
+        return vec![];
+    }
+    let chunk_size = pairs.len().div_ceil(threads.max(1));
+    thread::scope(|scope| {
+        let workers = pairs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut adjacencies = Adjacencies::new(edges);
+                    chunk
+                        .iter()
+                        .map(|(source, sink)| {
+                            adjacencies.reset_adjustments();
+                            max_flow(source, sink, &mut adjacencies, max_distance).0
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+/// Computes the maximum flow values from each of `sources` to each of `targets`.
+/// Row `i` of the result contains the flows from `sources[i]`.
+pub fn compute_max_flow_matrix(
+    sources: &[Address],
+    targets: &[Address],
+    edges: &EdgeDB,
+    max_distance: Option<u64>,
+    threads: usize,
+) -> Vec<Vec<U256>> {
+    let pairs = sources
+        .iter()
+        .flat_map(|source| targets.iter().map(|target| (*source, *target)))
+        .collect::<Vec<_>>();
+    let flows = compute_max_flows(&pairs, edges, max_distance, threads);
+    if targets.is_empty() {
+        return sources.iter().map(|_| vec![]).collect();
+    }
+    flows
+        .chunks(targets.len())
+        .map(|row| row.to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::compute_flow;
+    use crate::types::Edge;
+
+    #[test]
+    fn matrix() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        let edge = |from, to, token, capacity: u128| Edge {
+            from,
+            to,
+            token,
+            capacity: U256::from(capacity),
+        };
+        let edges = EdgeDB::new(vec![
+            edge(a, b, a, 10),
+            edge(b, c, b, 5),
+            edge(b, d, b, 8),
+            edge(a, d, a, 3),
+        ]);
+        let addresses = [a, b, c, d];
+        for threads in [1, 3, 16] {
+            let matrix = compute_max_flow_matrix(&addresses, &addresses, &edges, None, threads);
+            for (i, source) in addresses.iter().enumerate() {
+                for (j, target) in addresses.iter().enumerate() {
+                    let (flow, _) = compute_flow(source, target, &edges, U256::MAX, None, None);
+                    assert_eq!(matrix[i][j], flow);
+                }
+            }
+        }
+        assert_eq!(
+            compute_max_flows(&[(a, d), (a, c)], &edges, Some(1), 2),
+            vec![U256::from(3), U256::from(0)]
+        );
+        assert_eq!(
+            compute_max_flow_matrix(&[a], &[], &edges, None, 2),
+            vec![vec![]]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, U256};
+
+/// A Gomory-Hu tree (more precisely a flow-equivalent tree built with Gusfield's algorithm)
+/// over the undirected graph of addresses, where the capacity between two addresses is the
+/// sum of the capacities of all edges between them in both directions and all tokens.
+/// The minimum cut between any two addresses in that graph is the smallest capacity on the
+/// path between them in the tree.
+///
+/// This is not the max flow between two addresses: the flow network of `compute_flow` is
+/// directed, which a tree cannot represent, and it is further limited by balances and trust.
+/// The undirected min cut is an upper bound for the max flow in either direction, which
+/// makes it suitable for finding weakly connected clusters. The tree has to be rebuilt
+/// whenever the edges change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<TreeEdge>", into = "Vec<TreeEdge>")]
+pub struct UndirectedCutTree {
+    addresses: Vec<Address>,
+    /// Index of the parent of each node, the root (index 0) is its own parent.
+    parents: Vec<usize>,
+    /// Capacity of the edge to the parent.
+    capacities: Vec<U256>,
+    depths: Vec<usize>,
+    index: HashMap<Address, usize>,
+}
+
+/// Serialized form of a node of the tree and the edge to its parent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TreeEdge {
+    address: Address,
+    /// `None` for the root.
+    parent: Option<Address>,
+    capacity: U256,
+}
+
+impl UndirectedCutTree {
+    pub fn build(edges: &EdgeDB) -> UndirectedCutTree {
+        let addresses = edges
+            .edges()
+            .iter()
+            .flat_map(|e| [e.from, e.to])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let index = index_of(&addresses);
+        let mut capacities: Vec<HashMap<usize, U256>> = vec![HashMap::new(); addresses.len()];
+        for edge in edges.edges() {
+            let (from, to) = (index[&edge.from], index[&edge.to]);
+            if from == to {
+                continue;
+            }
+            for (a, b) in [(from, to), (to, from)] {
+                let capacity = capacities[a].entry(b).or_default();
+                *capacity = capacity.saturating_add(edge.capacity);
+            }
+        }
+
+        let mut parents = vec![0; addresses.len()];
+        let mut tree_capacities = vec![U256::MAX; addresses.len()];
+        for source in 1..addresses.len() {
+            let sink = parents[source];
+            let (flow, source_side) = min_cut(&capacities, source, sink);
+            tree_capacities[source] = flow;
+            for (node, parent) in parents.iter_mut().enumerate().skip(source + 1) {
+                if *parent == sink && source_side[node] {
+                    *parent = source;
+                }
+            }
+        }
+        UndirectedCutTree::new(addresses, parents, tree_capacities)
+    }
+
+    fn new(
+        addresses: Vec<Address>,
+        parents: Vec<usize>,
+        capacities: Vec<U256>,
+    ) -> UndirectedCutTree {
+        let mut depths = vec![0; addresses.len()];
+        for node in topological_order(&parents) {
+            if node != parents[node] {
+                depths[node] = depths[parents[node]] + 1;
+            }
+        }
+        UndirectedCutTree {
+            index: index_of(&addresses),
+            addresses,
+            parents,
+            capacities,
+            depths,
+        }
+    }
+
+    pub fn addresses(&self) -> &Vec<Address> {
+        &self.addresses
+    }
+
+    /// Returns the value of the minimum cut between `a` and `b` in the undirected graph,
+    /// or `None` if one of them is not part of the tree. This is an upper bound for the
+    /// max flow between them. Takes time linear in the depth of the tree.
+    pub fn undirected_min_cut(&self, a: &Address, b: &Address) -> Option<U256> {
+        let (mut a, mut b) = (*self.index.get(a)?, *self.index.get(b)?);
+        let mut result = U256::MAX;
+        while a != b {
+            if self.depths[a] < self.depths[b] {
+                (a, b) = (b, a);
+            }
+            if self.parents[a] == a {
+                // Both are roots, which a valid tree does not have.
+                return None;
+            }
+            result = result.min(self.capacities[a]);
+            a = self.parents[a];
+        }
+        Some(result)
+    }
+}
+
+/// Checks that the edges form a single tree: every address appears once, every parent
+/// is known and there is exactly one root and no cycle.
+impl TryFrom<Vec<TreeEdge>> for UndirectedCutTree {
+    type Error = String;
+    fn try_from(tree_edges: Vec<TreeEdge>) -> Result<Self, Self::Error> {
+        let addresses = tree_edges.iter().map(|e| e.address).collect::<Vec<_>>();
+        let index = index_of(&addresses);
+        if index.len() != addresses.len() {
+            return Err("Duplicate address in cut tree".to_string());
+        }
+        let parents = tree_edges
+            .iter()
+            .enumerate()
+            .map(|(i, e)| match e.parent {
+                Some(parent) => index
+                    .get(&parent)
+                    .copied()
+                    .ok_or_else(|| format!("Unknown parent {parent} of {}", e.address)),
+                None => Ok(i),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let roots = parents.iter().enumerate().filter(|(i, p)| i == *p).count();
+        if !addresses.is_empty() && roots != 1 {
+            return Err(format!("Expected one root in cut tree, found {roots}"));
+        }
+        // Nodes on a cycle are not reachable from the root.
+        if topological_order(&parents).len() != parents.len() {
+            return Err("Cycle in cut tree".to_string());
+        }
+        let capacities = tree_edges.iter().map(|e| e.capacity).collect();
+        Ok(UndirectedCutTree::new(addresses, parents, capacities))
+    }
+}
+
+impl From<UndirectedCutTree> for Vec<TreeEdge> {
+    fn from(tree: UndirectedCutTree) -> Self {
+        tree.addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| TreeEdge {
+                address: *address,
+                parent: (tree.parents[i] != i).then(|| tree.addresses[tree.parents[i]]),
+                capacity: tree.capacities[i],
+            })
+            .collect()
+    }
+}
+
+fn index_of(addresses: &[Address]) -> HashMap<Address, usize> {
+    addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| (*address, i))
+        .collect()
+}
+
+/// Returns the nodes such that each node comes after its parent.
+fn topological_order(parents: &[usize]) -> Vec<usize> {
+    let mut children: Vec<Vec<usize>> = vec![vec![]; parents.len()];
+    let mut queue = VecDeque::new();
+    for (node, parent) in parents.iter().enumerate() {
+        if node == *parent {
+            queue.push_back(node);
+        } else {
+            children[*parent].push(node);
+        }
+    }
+    let mut order = vec![];
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        queue.extend(&children[node]);
+    }
+    order
+}
+
+/// Computes a maximum flow in the undirected graph given by `capacities` and returns
+/// its value together with the set of nodes on the source side of a minimum cut.
+fn min_cut(capacities: &[HashMap<usize, U256>], source: usize, sink: usize) -> (U256, Vec<bool>) {
+    let mut residual = capacities.to_vec();
+    let mut flow = U256::from(0);
+    loop {
+        let mut parent = vec![None; capacities.len()];
+        parent[source] = Some(source);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            if node == sink {
+                break;
+            }
+            for (next, capacity) in &residual[node] {
+                if parent[*next].is_none() && *capacity != U256::from(0) {
+                    parent[*next] = Some(node);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        if parent[sink].is_none() {
+            return (flow, parent.iter().map(Option::is_some).collect());
+        }
+        let mut path = vec![];
+        let mut node = sink;
+        while node != source {
+            let prev = parent[node].unwrap();
+            path.push((prev, node));
+            node = prev;
+        }
+        let bottleneck = path.iter().map(|(a, b)| residual[*a][b]).min().unwrap();
+        for (a, b) in path {
+            *residual[a].get_mut(&b).unwrap() -= bottleneck;
+            let reverse = residual[b].entry(a).or_default();
+            *reverse = reverse.saturating_add(bottleneck);
+        }
+        flow = flow.saturating_add(bottleneck);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Edge;
+
+    #[test]
+    fn cut_tree() {
+        let [a, b, c, d, e] = [1, 2, 3, 4, 5].map(|i| Address::from([i; 20]));
+        let edge = |from, to, capacity: u128| Edge {
+            from,
+            to,
+            token: from,
+            capacity: U256::from(capacity),
+        };
+        // Two clusters {a, b, c} and {d, e}, connected by c -> d.
+        let edges = EdgeDB::new(vec![
+            edge(a, b, 10),
+            edge(b, a, 5),
+            edge(b, c, 7),
+            edge(a, c, 4),
+            edge(c, d, 2),
+            edge(d, e, 9),
+        ]);
+        let tree = UndirectedCutTree::build(&edges);
+        let cut = |x, y| tree.undirected_min_cut(&x, &y).unwrap();
+        assert_eq!(cut(a, b), U256::from(19));
+        assert_eq!(cut(b, a), U256::from(19));
+        assert_eq!(cut(a, c), U256::from(11));
+        assert_eq!(cut(b, c), U256::from(11));
+        assert_eq!(cut(a, d), U256::from(2));
+        assert_eq!(cut(e, b), U256::from(2));
+        assert_eq!(cut(d, e), U256::from(9));
+        assert_eq!(cut(a, a), U256::MAX);
+        assert_eq!(tree.undirected_min_cut(&a, &Address::from([9; 20])), None);
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: UndirectedCutTree = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, tree);
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let [a, b, c] = [1, 2, 3].map(|i| Address::from([i; 20]));
+        let tree_edge = |address, parent| TreeEdge {
+            address,
+            parent,
+            capacity: U256::from(1),
+        };
+        let parse = |tree_edges: Vec<TreeEdge>| {
+            let json = serde_json::to_string(&tree_edges).unwrap();
+            serde_json::from_str::<UndirectedCutTree>(&json)
+        };
+        assert!(parse(vec![tree_edge(a, None), tree_edge(b, Some(a))]).is_ok());
+        // Unknown parent.
+        assert!(parse(vec![tree_edge(a, None), tree_edge(b, Some(c))]).is_err());
+        // Two roots.
+        assert!(parse(vec![tree_edge(a, None), tree_edge(b, None)]).is_err());
+        // Cycle.
+        assert!(parse(vec![
+            tree_edge(a, None),
+            tree_edge(b, Some(c)),
+            tree_edge(c, Some(b))
+        ])
+        .is_err());
+        // Duplicate address.
+        assert!(parse(vec![tree_edge(a, None), tree_edge(a, Some(a))]).is_err());
+    }
+}
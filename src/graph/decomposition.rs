@@ -0,0 +1,161 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Address, Edge, U256};
+
+/// A path from the source to the sink of a flow, carrying `amount` tokens.
+/// The i-th hop sends tokens of `tokens[i]` from `addresses[i]` to `addresses[i + 1]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowPath {
+    pub amount: U256,
+    pub addresses: Vec<Address>,
+    pub tokens: Vec<Address>,
+}
+
+impl FlowPath {
+    /// Returns the hops of the path as edges with the amount of the path as capacity.
+    pub fn edges(&self) -> Vec<Edge> {
+        self.addresses
+            .windows(2)
+            .zip(&self.tokens)
+            .map(|(pair, token)| Edge {
+                from: pair[0],
+                to: pair[1],
+                token: *token,
+                capacity: self.amount,
+            })
+            .collect()
+    }
+}
+
+/// Decomposes the flow given by `transfers` (as returned by `compute_flow`) into paths
+/// from `source` to `sink`. Shorter paths are extracted first. The amounts of the paths
+/// add up to the flow, transfers that only form cycles are not part of any path.
+pub fn decompose_flow(source: &Address, sink: &Address, transfers: &[Edge]) -> Vec<FlowPath> {
+    if source == sink {
+        return vec![];
+    }
+    let mut remaining = transfers.iter().map(|e| e.capacity).collect::<Vec<_>>();
+    let mut paths = vec![];
+    while let Some(path) = shortest_path(source, sink, transfers, &remaining) {
+        let amount = path.iter().map(|i| remaining[*i]).min().unwrap();
+        for i in &path {
+            remaining[*i] -= amount;
+        }
+        let mut addresses = vec![*source];
+        addresses.extend(path.iter().map(|i| transfers[*i].to));
+        paths.push(FlowPath {
+            amount,
+            addresses,
+            tokens: path.iter().map(|i| transfers[*i].token).collect(),
+        });
+    }
+    paths
+}
+
+/// Returns the indices of the transfers on a shortest path from `source` to `sink`
+/// using only transfers with remaining capacity.
+fn shortest_path(
+    source: &Address,
+    sink: &Address,
+    transfers: &[Edge],
+    remaining: &[U256],
+) -> Option<Vec<usize>> {
+    // For each reached address, the index of the transfer it was reached by.
+    let mut reached_by: HashMap<Address, Option<usize>> = HashMap::from([(*source, None)]);
+    let mut queue = VecDeque::from([*source]);
+    while let Some(address) = queue.pop_front() {
+        if address == *sink {
+            let mut path = vec![];
+            let mut current = address;
+            while let Some(i) = reached_by[&current] {
+                path.push(i);
+                current = transfers[i].from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (i, transfer) in transfers.iter().enumerate() {
+            if transfer.from == address
+                && remaining[i] != U256::from(0)
+                && !reached_by.contains_key(&transfer.to)
+            {
+                reached_by.insert(transfer.to, Some(i));
+                queue.push_back(transfer.to);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transfer(from: Address, to: Address, token: Address, capacity: u128) -> Edge {
+        Edge {
+            from,
+            to,
+            token,
+            capacity: U256::from(capacity),
+        }
+    }
+
+    #[test]
+    fn decompose() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        // a sends 9 via b and 7 via c, b forwards 4 of the 9 via c.
+        let transfers = vec![
+            transfer(a, b, a, 9),
+            transfer(a, c, a, 7),
+            transfer(b, d, b, 5),
+            transfer(b, c, b, 4),
+            transfer(c, d, c, 11),
+        ];
+        let paths = decompose_flow(&a, &d, &transfers);
+        assert_eq!(
+            paths,
+            vec![
+                FlowPath {
+                    amount: U256::from(5),
+                    addresses: vec![a, b, d],
+                    tokens: vec![a, b],
+                },
+                FlowPath {
+                    amount: U256::from(7),
+                    addresses: vec![a, c, d],
+                    tokens: vec![a, c],
+                },
+                FlowPath {
+                    amount: U256::from(4),
+                    addresses: vec![a, b, c, d],
+                    tokens: vec![a, b, c],
+                },
+            ]
+        );
+        assert_eq!(
+            paths[2].edges(),
+            vec![
+                transfer(a, b, a, 4),
+                transfer(b, c, b, 4),
+                transfer(c, d, c, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn no_flow() {
+        let [a, b] = [1, 2].map(|i| Address::from([i; 20]));
+        assert_eq!(decompose_flow(&a, &b, &[]), vec![]);
+        assert_eq!(decompose_flow(&a, &b, &[transfer(b, a, b, 1)]), vec![]);
+    }
+
+    #[test]
+    fn source_is_sink() {
+        let [a, b] = [1, 2].map(|i| Address::from([i; 20]));
+        assert_eq!(decompose_flow(&a, &a, &[]), vec![]);
+        let cycle = [transfer(a, b, a, 1), transfer(b, a, b, 1)];
+        assert_eq!(decompose_flow(&a, &a, &cycle), vec![]);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::adjacencies::Adjacencies;
+use crate::graph::flow::max_flow;
+use crate::graph::Node;
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, AddressBook, U256};
+
+/// Kind of an edge of the flow network that limits the flow, see the description
+/// of the flow network in `graph/mod.rs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BottleneckKind {
+    /// The balance of `from` in `token` tokens (edge `from -> BalanceNode(from, token)`).
+    SenderBalance,
+    /// The send limit from `from` to `to` in `token` tokens
+    /// (edge `BalanceNode(from, token) -> TrustNode(to, token)`).
+    SendLimit,
+    /// The amount of `token` tokens `to` accepts in total
+    /// (edge `TrustNode(to, token) -> to`).
+    TrustLimit,
+}
+
+/// A saturated edge of a minimum cut between the source and the sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bottleneck {
+    pub kind: BottleneckKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    pub token: Address,
+    pub capacity: U256,
+    /// What could be done to increase the capacity.
+    pub suggestion: String,
+}
+
+/// Explains why the maximum flow is lower than the requested flow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowExplanation {
+    pub requested_flow: U256,
+    pub max_flow: U256,
+    /// True if the flow is only limited by the maximum distance, i.e. more flow
+    /// would be possible along longer paths.
+    pub distance_limited: bool,
+    pub sender_balance: Vec<Bottleneck>,
+    pub send_limit: Vec<Bottleneck>,
+    pub trust_limit: Vec<Bottleneck>,
+}
+
+/// Computes the maximum flow from `source` to `sink` and, if it is lower than
+/// `requested_flow`, the edges of a minimum cut grouped by kind.
+/// The capacities of the bottlenecks add up to the maximum flow, unless the flow
+/// is limited by `max_distance`. The suggestions refer to addresses by their `names`.
+pub fn explain_flow(
+    source: &Address,
+    sink: &Address,
+    edges: &EdgeDB,
+    requested_flow: U256,
+    max_distance: Option<u64>,
+    names: &AddressBook,
+) -> FlowExplanation {
+    let mut adjacencies = Adjacencies::new(edges);
+    let (flow, _, _) = max_flow(source, sink, &mut adjacencies, max_distance);
+    let mut explanation = FlowExplanation {
+        requested_flow,
+        max_flow: flow,
+        distance_limited: false,
+        sender_balance: vec![],
+        send_limit: vec![],
+        trust_limit: vec![],
+    };
+    if flow >= requested_flow || source == sink {
+        return explanation;
+    }
+
+    // The nodes reachable from the source in the residual network form
+    // the source side of a minimum cut.
+    let mut reachable = HashSet::from([Node::Node(*source)]);
+    let mut queue = VecDeque::from([Node::Node(*source)]);
+    while let Some(node) = queue.pop_front() {
+        for (target, _) in adjacencies.outgoing_edges_sorted_by_capacity(&node) {
+            if reachable.insert(target.clone()) {
+                queue.push_back(target);
+            }
+        }
+    }
+    if reachable.contains(&Node::Node(*sink)) {
+        explanation.distance_limited = true;
+        return explanation;
+    }
+
+    let mut reachable = reachable.into_iter().collect::<Vec<_>>();
+    reachable.sort();
+    for node in &reachable {
+        let mut cut_edges = adjacencies
+            .adjacencies_from(node)
+            .into_iter()
+            .filter(|(target, capacity)| {
+                *capacity != U256::from(0) && reachable.binary_search(target).is_err()
+            })
+            .collect::<Vec<_>>();
+        cut_edges.sort();
+        for (target, capacity) in cut_edges {
+            match (node, &target) {
+                (Node::Node(from), Node::BalanceNode(_, token)) => {
+                    let suggestion = if from == token {
+                        format!("wait for {} to mint more tokens", names.describe(from))
+                    } else {
+                        format!(
+                            "ask someone to send {} tokens to {}",
+                            names.describe(token),
+                            names.describe(from)
+                        )
+                    };
+                    explanation.sender_balance.push(Bottleneck {
+                        kind: BottleneckKind::SenderBalance,
+                        from: Some(*from),
+                        to: None,
+                        token: *token,
+                        capacity,
+                        suggestion,
+                    })
+                }
+                (Node::BalanceNode(from, token), Node::TrustNode(to, _)) => {
+                    explanation.send_limit.push(Bottleneck {
+                        kind: BottleneckKind::SendLimit,
+                        from: Some(*from),
+                        to: Some(*to),
+                        token: *token,
+                        capacity,
+                        suggestion: format!(
+                            "ask {to} to trust {token} more or to pass on its {token} tokens",
+                            to = names.describe(to),
+                            token = names.describe(token)
+                        ),
+                    })
+                }
+                (Node::TrustNode(to, token), Node::Node(_)) => {
+                    explanation.trust_limit.push(Bottleneck {
+                        kind: BottleneckKind::TrustLimit,
+                        from: None,
+                        to: Some(*to),
+                        token: *token,
+                        capacity,
+                        suggestion: format!(
+                            "ask {} to trust {} more",
+                            names.describe(to),
+                            names.describe(token)
+                        ),
+                    })
+                }
+                _ => panic!("Unexpected edge from {node} to {target}"),
+            }
+        }
+    }
+    for group in [
+        &mut explanation.sender_balance,
+        &mut explanation.send_limit,
+        &mut explanation.trust_limit,
+    ] {
+        group.sort_by_key(|b| std::cmp::Reverse(b.capacity));
+    }
+    explanation
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Edge;
+
+    fn edges(input: &[(Address, Address, Address, u128)]) -> EdgeDB {
+        EdgeDB::new(
+            input
+                .iter()
+                .map(|(from, to, token, capacity)| Edge {
+                    from: *from,
+                    to: *to,
+                    token: *token,
+                    capacity: U256::from(*capacity),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn send_limit() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        let edges = edges(&[(a, b, a, 10), (b, c, b, 5), (b, d, b, 8)]);
+        let explanation =
+            explain_flow(&a, &c, &edges, U256::from(8), None, &AddressBook::default());
+        assert_eq!(explanation.max_flow, U256::from(5));
+        assert!(!explanation.distance_limited);
+        assert!(explanation.sender_balance.is_empty());
+        assert!(explanation.trust_limit.is_empty());
+        assert_eq!(
+            explanation.send_limit,
+            vec![Bottleneck {
+                kind: BottleneckKind::SendLimit,
+                from: Some(b),
+                to: Some(c),
+                token: b,
+                capacity: U256::from(5),
+                suggestion: format!("ask {c} to trust {b} more or to pass on its {b} tokens"),
+            }]
+        );
+
+        let explanation =
+            explain_flow(&a, &c, &edges, U256::from(5), None, &AddressBook::default());
+        assert!(explanation.send_limit.is_empty());
+
+        let explanation = explain_flow(
+            &a,
+            &c,
+            &edges,
+            U256::from(5),
+            Some(1),
+            &AddressBook::default(),
+        );
+        assert_eq!(explanation.max_flow, U256::from(0));
+        assert!(explanation.distance_limited);
+    }
+
+    #[test]
+    fn trust_limit() {
+        let [a, b, c, e] = [1, 2, 3, 5].map(|i| Address::from([i; 20]));
+        let edges = edges(&[(a, b, a, 10), (a, c, e, 6), (b, c, e, 4)]);
+        let explanation = explain_flow(
+            &a,
+            &c,
+            &edges,
+            U256::from(10),
+            None,
+            &AddressBook::default(),
+        );
+        assert_eq!(explanation.max_flow, U256::from(6));
+        assert!(explanation.sender_balance.is_empty());
+        assert!(explanation.send_limit.is_empty());
+        assert_eq!(explanation.trust_limit.len(), 1);
+        assert_eq!(explanation.trust_limit[0].to, Some(c));
+        assert_eq!(explanation.trust_limit[0].token, e);
+        assert_eq!(explanation.trust_limit[0].capacity, U256::from(6));
+    }
+
+    #[test]
+    fn names() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        let edges = edges(&[(a, b, a, 10), (b, c, b, 5), (b, d, b, 8)]);
+        let names = AddressBook::from(std::collections::HashMap::from([(c, "carol".to_string())]));
+        let explanation = explain_flow(&a, &c, &edges, U256::from(8), None, &names);
+        assert_eq!(
+            explanation.send_limit[0].suggestion,
+            format!("ask carol ({c}) to trust {b} more or to pass on its {b} tokens")
+        );
+    }
+
+    #[test]
+    fn sender_balance() {
+        let [a, b] = [1, 2].map(|i| Address::from([i; 20]));
+        let edges = edges(&[(a, b, a, 3)]);
+        let explanation = explain_flow(&a, &b, &edges, U256::MAX, None, &AddressBook::default());
+        assert_eq!(explanation.max_flow, U256::from(3));
+        assert_eq!(explanation.sender_balance.len(), 1);
+        assert_eq!(explanation.sender_balance[0].from, Some(a));
+        assert_eq!(explanation.sender_balance[0].capacity, U256::from(3));
+        assert_eq!(
+            explanation.sender_balance[0].suggestion,
+            format!("wait for {} to mint more tokens", a.to_checksummed_hex())
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use crate::graph::adjacencies::Adjacencies;
+use crate::graph::Node;
+use crate::types::edge::{eq_up_to_capacity, EdgeDB};
+use crate::types::{Address, AddressBook, Edge};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Dot,
+    GraphMl,
+    Gexf,
+}
+
+impl ExportFormat {
+    /// Determines the format from the extension of a file name.
+    pub fn from_file_name(name: &str) -> Option<ExportFormat> {
+        match name.rsplit_once('.')?.1.to_lowercase().as_str() {
+            "dot" | "gv" => Some(ExportFormat::Dot),
+            "graphml" => Some(ExportFormat::GraphMl),
+            "gexf" => Some(ExportFormat::Gexf),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Show the intermediate balance and trust nodes of the flow network.
+    pub expand: bool,
+    /// Edges to highlight, e.g. the transfers of a flow. Capacities are ignored.
+    pub highlight: Vec<Edge>,
+    /// Names of addresses, the short address is used for addresses without name.
+    pub labels: AddressBook,
+}
+
+/// Returns all edges between addresses that are at most `hops` hops away from `center`,
+/// following edges in both directions.
+pub fn neighborhood(edges: &EdgeDB, center: &Address, hops: usize) -> Vec<Edge> {
+    let mut distance = HashMap::from([(*center, 0)]);
+    let mut queue = VecDeque::from([*center]);
+    while let Some(address) = queue.pop_front() {
+        if distance[&address] == hops {
+            continue;
+        }
+        let neighbors = edges
+            .outgoing(&address)
+            .into_iter()
+            .map(|e| e.to)
+            .chain(edges.incoming(&address).into_iter().map(|e| e.from))
+            .collect::<Vec<_>>();
+        for neighbor in neighbors {
+            if !distance.contains_key(&neighbor) {
+                distance.insert(neighbor, distance[&address] + 1);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    let mut result = distance
+        .keys()
+        .flat_map(|address| edges.outgoing(address))
+        .filter(|e| distance.contains_key(&e.to))
+        .copied()
+        .collect::<Vec<_>>();
+    result.sort();
+    result
+}
+
+/// An edge of the exported graph.
+struct ExportEdge {
+    from: String,
+    to: String,
+    label: String,
+    highlighted: bool,
+}
+
+/// Renders the graph formed by `edges` in the given format.
+pub fn export_graph(edges: &[Edge], format: ExportFormat, options: &ExportOptions) -> String {
+    let (nodes, export_edges) = build(edges, options);
+    match format {
+        ExportFormat::Dot => to_dot(&nodes, &export_edges),
+        ExportFormat::GraphMl => to_graphml(&nodes, &export_edges),
+        ExportFormat::Gexf => to_gexf(&nodes, &export_edges),
+    }
+}
+
+fn node_id(node: &Node) -> String {
+    match node {
+        Node::Node(address) => format!("{address}"),
+        Node::BalanceNode(from, token) => format!("bal:{from}:{token}"),
+        Node::TrustNode(to, token) => format!("trust:{to}:{token}"),
+    }
+}
+
+/// Returns the nodes (id to label) and edges of the graph to export.
+fn build(edges: &[Edge], options: &ExportOptions) -> (BTreeMap<String, String>, Vec<ExportEdge>) {
+    let label = |address: &Address| options.labels.label(address);
+    let is_highlighted = |edge: &Edge| options.highlight.iter().any(|h| eq_up_to_capacity(h, edge));
+    let addresses = edges
+        .iter()
+        .flat_map(|e| [e.from, e.to])
+        .collect::<BTreeSet<_>>();
+    let mut nodes = addresses
+        .iter()
+        .map(|a| (node_id(&Node::Node(*a)), label(a)))
+        .collect::<BTreeMap<_, _>>();
+    let mut export_edges = vec![];
+    if !options.expand {
+        for edge in edges {
+            let token = if edge.token == edge.from {
+                String::new()
+            } else {
+                format!(" ({})", label(&edge.token))
+            };
+            export_edges.push(ExportEdge {
+                from: node_id(&Node::Node(edge.from)),
+                to: node_id(&Node::Node(edge.to)),
+                label: format!("{}{token}", edge.capacity.to_crc()),
+                highlighted: is_highlighted(edge),
+            });
+        }
+        return (nodes, export_edges);
+    }
+
+    // Use the capacities of the flow network restricted to the exported edges.
+    let db = EdgeDB::new(edges.to_vec());
+    let mut adjacencies = Adjacencies::new(&db);
+    let highlighted_edges = edges
+        .iter()
+        .filter(|e| is_highlighted(e))
+        .flat_map(|e| {
+            let balance = Node::BalanceNode(e.from, e.token);
+            let trust = Node::TrustNode(e.to, e.token);
+            [
+                (Node::Node(e.from), balance.clone()),
+                (balance, trust.clone()),
+                (trust, Node::Node(e.to)),
+            ]
+        })
+        .collect::<HashSet<_>>();
+    let mut pending = addresses.iter().map(|a| Node::Node(*a)).collect::<Vec<_>>();
+    let mut visited = pending.iter().cloned().collect::<HashSet<_>>();
+    while let Some(node) = pending.pop() {
+        let mut targets = adjacencies
+            .adjacencies_from(&node)
+            .into_iter()
+            .collect::<Vec<_>>();
+        targets.sort();
+        for (target, capacity) in targets {
+            let highlighted = highlighted_edges.contains(&(node.clone(), target.clone()));
+            export_edges.push(ExportEdge {
+                from: node_id(&node),
+                to: node_id(&target),
+                label: capacity.to_crc(),
+                highlighted,
+            });
+            if visited.insert(target.clone()) {
+                let id = node_id(&target);
+                let name = match &target {
+                    Node::Node(address) => label(address),
+                    Node::BalanceNode(from, token) => {
+                        format!("bal {} x {}", label(from), label(token))
+                    }
+                    Node::TrustNode(to, token) => {
+                        format!("trust {} x {}", label(to), label(token))
+                    }
+                };
+                nodes.insert(id, name);
+                pending.push(target);
+            }
+        }
+    }
+    export_edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    (nodes, export_edges)
+}
+
+fn escape_dot(input: &str) -> String {
+    input.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_dot(nodes: &BTreeMap<String, String>, edges: &[ExportEdge]) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph neighborhood {{").expect("");
+    for (id, label) in nodes {
+        let shape = if id.starts_with("0x") {
+            "ellipse"
+        } else {
+            "box"
+        };
+        writeln!(
+            out,
+            "    \"{id}\" [label=\"{}\", shape={shape}];",
+            escape_dot(label)
+        )
+        .expect("");
+    }
+    for edge in edges {
+        let style = if edge.highlighted {
+            ", color=red, penwidth=2"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "    \"{}\" -> \"{}\" [label=\"{}\"{style}];",
+            edge.from,
+            edge.to,
+            escape_dot(&edge.label)
+        )
+        .expect("");
+    }
+    writeln!(out, "}}").expect("");
+    out
+}
+
+fn to_graphml(nodes: &BTreeMap<String, String>, edges: &[ExportEdge]) -> String {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).expect("");
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )
+    .expect("");
+    writeln!(
+        out,
+        r#"  <key id="label" for="all" attr.name="label" attr.type="string"/>"#
+    )
+    .expect("");
+    writeln!(
+        out,
+        r#"  <key id="used" for="edge" attr.name="used" attr.type="boolean"/>"#
+    )
+    .expect("");
+    writeln!(out, r#"  <graph id="neighborhood" edgedefault="directed">"#).expect("");
+    for (id, label) in nodes {
+        writeln!(
+            out,
+            r#"    <node id="{}"><data key="label">{}</data></node>"#,
+            escape_xml(id),
+            escape_xml(label)
+        )
+        .expect("");
+    }
+    for edge in edges {
+        writeln!(
+            out,
+            r#"    <edge source="{}" target="{}"><data key="label">{}</data><data key="used">{}</data></edge>"#,
+            escape_xml(&edge.from),
+            escape_xml(&edge.to),
+            escape_xml(&edge.label),
+            edge.highlighted
+        )
+        .expect("");
+    }
+    writeln!(out, "  </graph>").expect("");
+    writeln!(out, "</graphml>").expect("");
+    out
+}
+
+fn to_gexf(nodes: &BTreeMap<String, String>, edges: &[ExportEdge]) -> String {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).expect("");
+    writeln!(out, r#"<gexf xmlns="http://gexf.net/1.2" version="1.2">"#).expect("");
+    writeln!(out, r#"  <graph defaultedgetype="directed">"#).expect("");
+    writeln!(out, r#"    <attributes class="edge">"#).expect("");
+    writeln!(
+        out,
+        r#"      <attribute id="0" title="used" type="boolean"/>"#
+    )
+    .expect("");
+    writeln!(out, "    </attributes>").expect("");
+    writeln!(out, "    <nodes>").expect("");
+    for (id, label) in nodes {
+        writeln!(
+            out,
+            r#"      <node id="{}" label="{}"/>"#,
+            escape_xml(id),
+            escape_xml(label)
+        )
+        .expect("");
+    }
+    writeln!(out, "    </nodes>").expect("");
+    writeln!(out, "    <edges>").expect("");
+    for (i, edge) in edges.iter().enumerate() {
+        writeln!(
+            out,
+            r#"      <edge id="{i}" source="{}" target="{}" label="{}"><attvalues><attvalue for="0" value="{}"/></attvalues></edge>"#,
+            escape_xml(&edge.from),
+            escape_xml(&edge.to),
+            escape_xml(&edge.label),
+            edge.highlighted
+        )
+        .expect("");
+    }
+    writeln!(out, "    </edges>").expect("");
+    writeln!(out, "  </graph>").expect("");
+    writeln!(out, "</gexf>").expect("");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::U256;
+
+    fn edges() -> (Vec<Address>, EdgeDB) {
+        let addresses = [1, 2, 3, 4].map(|i| Address::from([i; 20])).to_vec();
+        let edge = |from: usize, to: usize, token: usize| Edge {
+            from: addresses[from],
+            to: addresses[to],
+            token: addresses[token],
+            capacity: U256::from(1000000000000000000),
+        };
+        // A chain 0 -> 1 -> 2 -> 3.
+        let edges = EdgeDB::new(vec![edge(0, 1, 0), edge(1, 2, 1), edge(2, 3, 2)]);
+        (addresses, edges)
+    }
+
+    #[test]
+    fn hops() {
+        let (a, edges) = edges();
+        assert_eq!(neighborhood(&edges, &a[1], 0), vec![]);
+        let around = neighborhood(&edges, &a[1], 1);
+        assert_eq!(around.len(), 2);
+        assert!(around.iter().all(|e| e.to != a[3]));
+        assert_eq!(neighborhood(&edges, &a[0], 3).len(), 3);
+    }
+
+    #[test]
+    fn formats() {
+        let (a, edges) = edges();
+        let subgraph = neighborhood(&edges, &a[0], 1);
+        let options = ExportOptions {
+            expand: false,
+            highlight: subgraph.clone(),
+            labels: AddressBook::from(HashMap::from([(a[0], "Alice <A>".to_string())])),
+        };
+        let dot = export_graph(&subgraph, ExportFormat::Dot, &options);
+        assert!(dot.contains(&format!(
+            "\"{}\" [label=\"Alice <A>\", shape=ellipse];",
+            a[0]
+        )));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [label=\"1\", color=red, penwidth=2];",
+            a[0], a[1]
+        )));
+        let graphml = export_graph(&subgraph, ExportFormat::GraphMl, &options);
+        assert!(graphml.contains("<data key=\"label\">Alice &lt;A&gt;</data>"));
+        assert!(graphml.contains("<data key=\"used\">true</data>"));
+        let gexf = export_graph(&subgraph, ExportFormat::Gexf, &options);
+        assert!(gexf.contains(&format!(
+            "<node id=\"{}\" label=\"Alice &lt;A&gt;\"/>",
+            a[0]
+        )));
+
+        let expanded = ExportOptions {
+            expand: true,
+            ..ExportOptions::default()
+        };
+        let dot = export_graph(&subgraph, ExportFormat::Dot, &expanded);
+        assert!(dot.contains(&format!("\"{0}\" -> \"bal:{0}:{0}\"", a[0])));
+        assert!(dot.contains(&format!("\"bal:{0}:{0}\" -> \"trust:{1}:{0}\"", a[0], a[1])));
+        assert!(dot.contains(&format!("\"trust:{1}:{0}\" -> \"{1}\"", a[0], a[1])));
+        assert!(!dot.contains("color=red"));
+        let highlighted = ExportOptions {
+            expand: true,
+            highlight: vec![subgraph[0]],
+            ..ExportOptions::default()
+        };
+        let dot = export_graph(&subgraph, ExportFormat::Dot, &highlighted);
+        assert_eq!(dot.matches("color=red").count(), 3);
+        assert_eq!(
+            ExportFormat::from_file_name("x.GEXF"),
+            Some(ExportFormat::Gexf)
+        );
+        assert_eq!(ExportFormat::from_file_name("x"), None);
+    }
+}
@@ -1,12 +1,23 @@
 use crate::graph::adjacencies::Adjacencies;
 use crate::graph::{as_trust_node, Node};
 use crate::types::edge::EdgeDB;
-use crate::types::{Address, Edge, U256};
+use crate::types::{Address, AddressBook, Edge, I256, U256};
 use std::cmp::min;
 use std::collections::{BTreeMap, HashSet};
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Write;
 
+/// Statistics collected during a flow computation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FlowStats {
+    /// Number of augmenting paths found.
+    pub augmenting_paths: u64,
+    /// Maximum flow before pruning it to the requested flow.
+    pub max_flow: U256,
+    /// Flow lost due to reducing the number of transfers.
+    pub transfer_reduction_loss: U256,
+}
+
 pub fn compute_flow(
     source: &Address,
     sink: &Address,
@@ -15,28 +26,98 @@ pub fn compute_flow(
     max_distance: Option<u64>,
     max_transfers: Option<u64>,
 ) -> (U256, Vec<Edge>) {
+    let (flow, transfers, _) = compute_flow_with_stats(
+        source,
+        sink,
+        edges,
+        requested_flow,
+        max_distance,
+        max_transfers,
+    );
+    (flow, transfers)
+}
+
+/// Like `compute_flow`, but also returns statistics about the computation.
+pub fn compute_flow_with_stats(
+    source: &Address,
+    sink: &Address,
+    edges: &EdgeDB,
+    requested_flow: U256,
+    max_distance: Option<u64>,
+    max_transfers: Option<u64>,
+) -> (U256, Vec<Edge>, FlowStats) {
     let mut adjacencies = Adjacencies::new(edges);
-    let mut used_edges: HashMap<Node, HashMap<Node, U256>> = HashMap::new();
+    let mut stats = FlowStats::default();
+    let (mut flow, flow_on_edges, augmenting_paths) =
+        max_flow(source, sink, &mut adjacencies, max_distance);
+    stats.augmenting_paths = augmenting_paths;
+
+    let mut used_edges = used_edges_from_flow(flow_on_edges);
+
+    log::debug!("Max flow: {}", flow.to_decimal());
+    stats.max_flow = flow;
+
+    if flow > requested_flow {
+        let still_to_prune = prune_flow(source, sink, flow - requested_flow, &mut used_edges);
+        flow = requested_flow + still_to_prune;
+    }
+
+    if let Some(max_transfers) = max_transfers {
+        let lost = reduce_transfers(max_transfers * 3, &mut used_edges);
+        log::debug!(
+            "Capacity lost by transfer count reduction: {}",
+            lost.to_decimal_fraction()
+        );
+        flow -= lost;
+        stats.transfer_reduction_loss = lost;
+    }
+
+    let transfers = if flow == U256::from(0) {
+        vec![]
+    } else {
+        extract_transfers(source, sink, &flow, used_edges)
+    };
+    log::debug!("Num transfers: {}", transfers.len());
+    let simplified_transfers = simplify_transfers(transfers);
+    log::debug!("After simplification: {}", simplified_transfers.len());
+    let sorted_transfers = sort_transfers(simplified_transfers);
+    (flow, sorted_transfers, stats)
+}
+
+/// Computes a maximum flow from `source` to `sink` by repeatedly finding augmenting paths.
+/// Returns the flow, the flow on each edge of the flow network and the number of
+/// augmenting paths. Afterwards, `adjacencies` contains the residual capacities.
+pub(crate) fn max_flow(
+    source: &Address,
+    sink: &Address,
+    adjacencies: &mut Adjacencies,
+    max_distance: Option<u64>,
+) -> (U256, HashMap<Node, HashMap<Node, I256>>, u64) {
+    let mut flow_on_edges: HashMap<Node, HashMap<Node, I256>> = HashMap::new();
+    let mut augmenting_paths = 0;
 
     let mut flow = U256::default();
     loop {
-        let (new_flow, parents) = augmenting_path(source, sink, &mut adjacencies, max_distance);
+        let (new_flow, parents) = augmenting_path(source, sink, adjacencies, max_distance);
         if new_flow == U256::default() {
             break;
         }
+        augmenting_paths += 1;
         flow += new_flow;
         for window in parents.windows(2) {
             if let [node, prev] = window {
+                let new_flow = I256::from(new_flow);
                 adjacencies.adjust_capacity(prev, node, -new_flow);
                 adjacencies.adjust_capacity(node, prev, new_flow);
                 if adjacencies.is_adjacent(node, prev) {
-                    *used_edges
+                    // We use the reverse of an edge, i.e. cancel flow on it.
+                    *flow_on_edges
                         .entry(node.clone())
                         .or_default()
                         .entry(prev.clone())
                         .or_default() -= new_flow;
                 } else {
-                    *used_edges
+                    *flow_on_edges
                         .entry(prev.clone())
                         .or_default()
                         .entry(node.clone())
@@ -47,41 +128,37 @@ pub fn compute_flow(
             }
         }
     }
+    (flow, flow_on_edges, augmenting_paths)
+}
 
-    used_edges.retain(|_, out| {
-        out.retain(|_, c| *c != U256::from(0));
-        !out.is_empty()
-    });
-
-    println!("Max flow: {}", flow.to_decimal());
-
-    if flow > requested_flow {
-        let still_to_prune = prune_flow(source, sink, flow - requested_flow, &mut used_edges);
-        flow = requested_flow + still_to_prune;
-    }
-
-    if let Some(max_transfers) = max_transfers {
-        let lost = reduce_transfers(max_transfers * 3, &mut used_edges);
-        println!(
-            "Capacity lost by transfer count reduction: {}",
-            lost.to_decimal_fraction()
-        );
-        flow -= lost;
-    }
-
-    let transfers = if flow == U256::from(0) {
-        vec![]
-    } else {
-        extract_transfers(source, sink, &flow, used_edges)
-    };
-    println!("Num transfers: {}", transfers.len());
-    let simplified_transfers = simplify_transfers(transfers);
-    println!("After simplification: {}", simplified_transfers.len());
-    let sorted_transfers = sort_transfers(simplified_transfers);
-    (flow, sorted_transfers)
+/// Converts the flow on the edges of the flow network into the used edges,
+/// removing edges without flow.
+fn used_edges_from_flow(
+    flow_on_edges: HashMap<Node, HashMap<Node, I256>>,
+) -> HashMap<Node, HashMap<Node, U256>> {
+    flow_on_edges
+        .into_iter()
+        .map(|(from, out)| {
+            let out = out
+                .into_iter()
+                .map(|(to, flow)| {
+                    // Flow can only be cancelled after it has been sent.
+                    let flow = flow
+                        .to_u256()
+                        .unwrap_or_else(|| panic!("Negative flow {flow} from {from} to {to}"));
+                    (to, flow)
+                })
+                .filter(|(_, flow)| *flow != U256::from(0))
+                .collect::<HashMap<_, _>>();
+            (from, out)
+        })
+        .filter(|(_, out)| !out.is_empty())
+        .collect()
 }
 
-pub fn transfers_to_dot(edges: &Vec<Edge>) -> String {
+/// Renders the transfers as a graph in dot format, labelling addresses by their names if known.
+pub fn transfers_to_dot(edges: &Vec<Edge>, names: &AddressBook) -> String {
+    let label = |address: &Address| names.label(address).replace('"', "\\\"");
     let mut out = String::new();
     writeln!(out, "digraph transfers {{").expect("");
 
@@ -97,13 +174,13 @@ pub fn transfers_to_dot(edges: &Vec<Edge>) -> String {
         } else if token == to {
             String::new()
         } else {
-            format!(" ({})", token.short())
+            format!(" ({})", label(token))
         };
         writeln!(
             out,
             "    \"{}\" -> \"{}\" [label=\"{}{}\"];",
-            from.short(),
-            to.short(),
+            label(from),
+            label(to),
             capacity.to_decimal_fraction(),
             t
         )
@@ -124,7 +201,7 @@ fn augmenting_path(
         return (U256::default(), vec![]);
     }
     let mut queue = VecDeque::<(Node, (u64, U256))>::new();
-    queue.push_back((Node::Node(*source), (0, U256::default() - U256::from(1))));
+    queue.push_back((Node::Node(*source), (0, U256::MAX)));
     while let Some((node, (depth, flow))) = queue.pop_front() {
         if let Some(max) = max_distance {
             // * 3 because we have three edges per trust connection (two intermediate nodes).
@@ -492,7 +569,7 @@ fn next_full_capacity_edge(
     panic!();
 }
 
-fn find_pair_to_simplify(transfers: &Vec<Edge>) -> Option<(usize, usize)> {
+fn find_pair_to_simplify(transfers: &[Edge]) -> Option<(usize, usize)> {
     let l = transfers.len();
     (0..l)
         .flat_map(move |x| (0..l).map(move |y| (x, y)))
@@ -748,4 +825,34 @@ mod test {
         println!("{:?}", &flow.1);
         assert_eq!(flow.0, U256::from(9));
     }
+
+    #[test]
+    fn cancel_flow() {
+        let [s, a, d, t, x, y, p, q] = [1, 2, 3, 4, 5, 6, 7, 8].map(|i| Address::from([i; 20]));
+        let edge = |from, to, token| Edge {
+            from,
+            to,
+            token,
+            capacity: U256::from(10),
+        };
+        // The shortest path s -> a -> d -> t has to be partially undone
+        // to achieve the maximum flow s -> a -> p -> q -> t plus s -> x -> y -> d -> t.
+        let edges = build_edges(vec![
+            edge(s, a, s),
+            edge(a, d, a),
+            edge(d, t, d),
+            edge(s, x, q),
+            edge(x, y, x),
+            edge(y, d, y),
+            edge(a, p, t),
+            edge(p, q, p),
+            edge(q, t, q),
+        ]);
+        let (flow, transfers, stats) =
+            compute_flow_with_stats(&s, &t, &edges, U256::MAX, None, None);
+        assert_eq!(flow, U256::from(20));
+        assert_eq!(stats.augmenting_paths, 2);
+        assert_eq!(transfers.len(), 8);
+        assert!(!transfers.iter().any(|e| e.from == a && e.to == d));
+    }
 }
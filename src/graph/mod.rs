@@ -2,7 +2,14 @@ use crate::types::Address;
 use std::fmt::{Display, Formatter};
 
 mod adjacencies;
+pub mod analytics;
+mod batch;
+mod cut_tree;
+mod decomposition;
+mod explain;
+mod export;
 mod flow;
+mod paths;
 
 // An edge from the capacity network is
 // from, token, to -> capacity
@@ -51,5 +58,23 @@ impl Display for Node {
     }
 }
 
+pub use crate::graph::batch::compute_max_flow_matrix;
+pub use crate::graph::batch::compute_max_flows;
+pub use crate::graph::cut_tree::UndirectedCutTree;
+pub use crate::graph::decomposition::decompose_flow;
+pub use crate::graph::decomposition::FlowPath;
+pub use crate::graph::explain::explain_flow;
+pub use crate::graph::explain::Bottleneck;
+pub use crate::graph::explain::BottleneckKind;
+pub use crate::graph::explain::FlowExplanation;
+pub use crate::graph::export::export_graph;
+pub use crate::graph::export::neighborhood;
+pub use crate::graph::export::ExportFormat;
+pub use crate::graph::export::ExportOptions;
 pub use crate::graph::flow::compute_flow;
+pub use crate::graph::flow::compute_flow_with_stats;
 pub use crate::graph::flow::transfers_to_dot;
+pub use crate::graph::flow::FlowStats;
+pub use crate::graph::paths::k_shortest_paths;
+pub use crate::graph::paths::PathCost;
+pub use crate::graph::paths::TrustPath;
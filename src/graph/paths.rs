@@ -0,0 +1,250 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, Edge, U256};
+
+/// How the length of a path is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathCost {
+    /// Number of hops.
+    #[default]
+    Hops,
+    /// Sum of the inverse capacities (in CRC) of the hops, so that hops with
+    /// a low capacity are expensive.
+    Capacity,
+}
+
+impl FromStr for PathCost {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hops" => Ok(PathCost::Hops),
+            "capacity" => Ok(PathCost::Capacity),
+            _ => Err(format!(
+                "Expected \"hops\" or \"capacity\", but got \"{s}\""
+            )),
+        }
+    }
+}
+
+impl PathCost {
+    fn of(&self, edge: &Edge) -> f64 {
+        match self {
+            PathCost::Hops => 1.0,
+            PathCost::Capacity => 1e18 / edge.capacity.to_f64(),
+        }
+    }
+}
+
+/// A path between two addresses in the capacity network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustPath {
+    pub cost: f64,
+    /// The smallest capacity of all hops.
+    pub capacity: U256,
+    /// Each hop is an edge with its token and capacity.
+    pub hops: Vec<Edge>,
+}
+
+impl TrustPath {
+    fn new(hops: Vec<Edge>, cost: PathCost) -> TrustPath {
+        TrustPath {
+            cost: hops.iter().map(|e| cost.of(e)).sum(),
+            capacity: hops.iter().map(|e| e.capacity).min().unwrap_or(U256::MAX),
+            hops,
+        }
+    }
+
+    /// Returns the addresses visited by the path, starting with the source.
+    pub fn addresses(&self) -> Vec<Address> {
+        self.hops
+            .first()
+            .map(|e| e.from)
+            .into_iter()
+            .chain(self.hops.iter().map(|e| e.to))
+            .collect()
+    }
+}
+
+/// Returns up to `k` shortest loopless paths from `source` to `sink` (Yen's algorithm),
+/// ordered by increasing cost. Paths are distinct if they differ in at least one hop,
+/// which includes using a different token between the same addresses.
+pub fn k_shortest_paths(
+    source: &Address,
+    sink: &Address,
+    edges: &EdgeDB,
+    k: usize,
+    cost: PathCost,
+) -> Vec<TrustPath> {
+    let mut result: Vec<TrustPath> = vec![];
+    if k == 0 || source == sink {
+        return result;
+    }
+    let Some(first) = shortest_path(source, sink, edges, cost, &HashSet::new(), &HashSet::new())
+    else {
+        return result;
+    };
+    result.push(TrustPath::new(first, cost));
+    let mut candidates: Vec<TrustPath> = vec![];
+    while result.len() < k {
+        let previous = &result[result.len() - 1];
+        for i in 0..previous.hops.len() {
+            let root = &previous.hops[..i];
+            let spur = previous.hops[i].from;
+            // Do not use the next hop of any path found so far with the same root.
+            let removed_edges = result
+                .iter()
+                .filter(|p| p.hops.len() > i && p.hops[..i] == *root)
+                .map(|p| p.hops[i])
+                .collect::<HashSet<_>>();
+            // The path has to be loopless.
+            let removed_addresses = root.iter().map(|e| e.from).collect::<HashSet<_>>();
+            if let Some(spur_path) =
+                shortest_path(&spur, sink, edges, cost, &removed_edges, &removed_addresses)
+            {
+                let path = TrustPath::new([root, &spur_path].concat(), cost);
+                if !candidates.iter().any(|c| c.hops == path.hops) {
+                    candidates.push(path);
+                }
+            }
+        }
+        let Some(best) = candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.cost.total_cmp(&b.cost))
+            .map(|(i, _)| i)
+        else {
+            break;
+        };
+        result.push(candidates.swap_remove(best));
+    }
+    result
+}
+
+/// Cost in the priority queue of Dijkstra's algorithm.
+#[derive(PartialEq)]
+struct Distance(f64);
+
+impl Eq for Distance {}
+
+impl PartialOrd for Distance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Distance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+fn shortest_path(
+    source: &Address,
+    sink: &Address,
+    edges: &EdgeDB,
+    cost: PathCost,
+    removed_edges: &HashSet<Edge>,
+    removed_addresses: &HashSet<Address>,
+) -> Option<Vec<Edge>> {
+    let mut distance: HashMap<Address, f64> = HashMap::from([(*source, 0.0)]);
+    let mut reached_by: HashMap<Address, Edge> = HashMap::new();
+    let mut queue = BinaryHeap::from([(Reverse(Distance(0.0)), *source)]);
+    while let Some((Reverse(Distance(d)), address)) = queue.pop() {
+        if address == *sink {
+            let mut path = vec![];
+            let mut current = address;
+            while current != *source {
+                let edge = reached_by[&current];
+                path.push(edge);
+                current = edge.from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if d > distance[&address] {
+            continue;
+        }
+        for edge in edges.outgoing(&address) {
+            if removed_edges.contains(edge)
+                || removed_addresses.contains(&edge.to)
+                || edge.to == *source
+            {
+                continue;
+            }
+            let new_distance = d + cost.of(edge);
+            if distance
+                .get(&edge.to)
+                .is_none_or(|current| new_distance < *current)
+            {
+                distance.insert(edge.to, new_distance);
+                reached_by.insert(edge.to, *edge);
+                queue.push((Reverse(Distance(new_distance)), edge.to));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn k_shortest() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        let edge = |from, to, token, capacity: u128| Edge {
+            from,
+            to,
+            token,
+            capacity: U256::from(capacity) * U256::from(1000000000000000000),
+        };
+        let edges = EdgeDB::new(vec![
+            edge(a, d, a, 1),
+            edge(a, b, a, 10),
+            edge(b, d, b, 10),
+            edge(b, d, a, 5),
+            edge(a, c, a, 10),
+            edge(c, b, c, 10),
+            edge(d, a, d, 10),
+        ]);
+        let paths = k_shortest_paths(&a, &d, &edges, 10, PathCost::Hops);
+        let addresses = paths.iter().map(|p| p.addresses()).collect::<Vec<_>>();
+        assert_eq!(
+            addresses,
+            vec![
+                vec![a, d],
+                vec![a, b, d],
+                vec![a, b, d],
+                vec![a, c, b, d],
+                vec![a, c, b, d],
+            ]
+        );
+        assert_eq!(paths[0].cost, 1.0);
+        assert_eq!(paths[3].cost, 3.0);
+        assert_eq!(paths[1].hops[1].token, b);
+        assert_eq!(paths[2].hops[1].token, a);
+
+        let paths = k_shortest_paths(&a, &d, &edges, 2, PathCost::Capacity);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].addresses(), vec![a, b, d]);
+        assert_eq!(paths[0].cost, 0.2);
+        assert_eq!(
+            paths[0].capacity,
+            U256::from(10) * U256::from(1000000000000000000)
+        );
+        assert_eq!(paths[1].addresses(), vec![a, c, b, d]);
+
+        // There is only one path from d to c.
+        let paths = k_shortest_paths(&d, &c, &edges, 3, PathCost::Hops);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].addresses(), vec![d, a, c]);
+        assert_eq!(paths[0].cost, 2.0);
+        assert!(k_shortest_paths(&c, &c, &edges, 3, PathCost::Hops).is_empty());
+    }
+}
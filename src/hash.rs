@@ -0,0 +1,24 @@
+use tiny_keccak::{Hasher, Keccak};
+
+/// Returns the keccak256 hash of `data`, as used by Ethereum.
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::keccak256;
+
+    #[test]
+    fn empty_input() {
+        let hash: String = keccak256(b"").iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hash,
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+}
@@ -4,9 +4,71 @@ use std::io::{self, BufRead};
 use std::io::{Read, Write};
 use std::{collections::HashMap, io::BufReader};
 
+use crate::graph::analytics::AddressStats;
+use crate::graph::UndirectedCutTree;
 use crate::safe_db::db::DB;
+use crate::safe_db::safes_json::import_from_safes_json;
 use crate::types::edge::EdgeDB;
-use crate::types::{Address, Edge, Safe, U256};
+use crate::types::{Address, AddressBook, Edge, Safe, U256};
+
+/// Formats of edge and safe databases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    EdgesBinary,
+    EdgesCsv,
+    EdgesJson,
+    SafesBinary,
+    SafesJson,
+}
+
+impl FileFormat {
+    pub fn contains_safes(&self) -> bool {
+        matches!(self, FileFormat::SafesBinary | FileFormat::SafesJson)
+    }
+}
+
+/// Detects the format of a file from its contents: JSON files start with `[` (edges)
+/// or `{` (safes), CSV files with an address. Binary files are recognized by checking
+/// that their structure matches the edges or safes format up to the last byte.
+pub fn detect_format(path: &str) -> Result<FileFormat, io::Error> {
+    let data = std::fs::read(path)?;
+    let text_start = data.iter().find(|b| !b.is_ascii_whitespace());
+    match text_start {
+        Some(b'[') => return Ok(FileFormat::EdgesJson),
+        Some(b'{') => return Ok(FileFormat::SafesJson),
+        _ if data.starts_with(b"0x") => return Ok(FileFormat::EdgesCsv),
+        _ => {}
+    }
+    if is_edges_binary(&data).is_some() {
+        Ok(FileFormat::EdgesBinary)
+    } else if is_safes_binary(&data).is_some() {
+        Ok(FileFormat::SafesBinary)
+    } else {
+        Err(io::Error::other(format!("Unknown file format of {path}")))
+    }
+}
+
+/// Reads the edges from a file in any of the formats, see `detect_format`.
+pub fn read_edges_file(path: &String) -> Result<EdgeDB, io::Error> {
+    Ok(match detect_format(path)? {
+        FileFormat::EdgesBinary => read_edges_binary(path)?,
+        FileFormat::EdgesCsv => read_edges_csv(path)?,
+        FileFormat::EdgesJson => read_edges_json(path)?,
+        FileFormat::SafesBinary => import_from_safes_binary(path)?.edges().clone(),
+        FileFormat::SafesJson => import_from_safes_json(path).edges().clone(),
+    })
+}
+
+/// Reads the safes from a file in the binary or JSON safes format.
+pub fn read_safes_file(path: &String) -> Result<DB, io::Error> {
+    match detect_format(path)? {
+        FileFormat::SafesBinary => import_from_safes_binary(path),
+        FileFormat::SafesJson => Ok(import_from_safes_json(path)),
+        _ => Err(io::Error::other(format!(
+            "{path} contains edges, but safes are required"
+        ))),
+    }
+}
 
 pub fn read_edges_binary(path: &String) -> Result<EdgeDB, io::Error> {
     let mut f = File::open(path)?;
@@ -34,16 +96,37 @@ pub fn read_edges_csv(path: &String) -> Result<EdgeDB, io::Error> {
                 });
             }
             _ => {
-                return Result::Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Expected from,to,token,capacity, but got {line}"),
-                ))
+                return Result::Err(io::Error::other(format!(
+                    "Expected from,to,token,capacity, but got {line}"
+                )))
             }
         }
     }
     Ok(EdgeDB::new(edges))
 }
 
+/// Reads names of addresses from a file with lines of the form `address,name`.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_address_book(path: &String) -> Result<AddressBook, io::Error> {
+    let mut labels = HashMap::new();
+    let f = BufReader::new(File::open(path)?);
+    for line in f.lines() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((address, label)) = line.split_once(',') else {
+            return Err(io::Error::other(format!(
+                "Expected address,label, but got {line}"
+            )));
+        };
+        let address = Address::parse_lenient(unescape(address.trim()))
+            .map_err(|e| io::Error::other(format!("Invalid address in {line}: {e}")))?;
+        labels.insert(address, unescape(label.trim()).to_string());
+    }
+    Ok(AddressBook::from(labels))
+}
+
 pub fn write_edges_binary(edges: &EdgeDB, path: &String) -> Result<(), io::Error> {
     let mut file = File::create(path)?;
     let address_index = write_address_index(&mut file, edges)?;
@@ -66,6 +149,47 @@ pub fn write_edges_csv(edges: &EdgeDB, path: &String) -> Result<(), io::Error> {
     Ok(())
 }
 
+pub fn read_edges_json(path: &String) -> Result<EdgeDB, io::Error> {
+    let f = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(f)?)
+}
+
+pub fn write_edges_json(edges: &EdgeDB, path: &String) -> Result<(), io::Error> {
+    let mut sorted_edges = edges.edges().clone();
+    sorted_edges.sort();
+    let mut file = File::create(path)?;
+    serde_json::to_writer(&mut file, &sorted_edges)?;
+    Ok(())
+}
+
+/// Writes the statistics of all addresses as CSV with a header line.
+pub fn write_address_stats_csv(stats: &[AddressStats], path: &String) -> Result<(), io::Error> {
+    let mut file = File::create(path)?;
+    writeln!(
+        file,
+        "address,in_degree,out_degree,component,pagerank,betweenness"
+    )?;
+    for s in stats {
+        writeln!(
+            file,
+            "{},{},{},{},{},{}",
+            s.address, s.in_degree, s.out_degree, s.component, s.pagerank, s.betweenness
+        )?;
+    }
+    Ok(())
+}
+
+pub fn read_cut_tree(path: &String) -> Result<UndirectedCutTree, io::Error> {
+    let f = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(f)?)
+}
+
+pub fn write_cut_tree(tree: &UndirectedCutTree, path: &String) -> Result<(), io::Error> {
+    let mut file = File::create(path)?;
+    serde_json::to_writer(&mut file, tree)?;
+    Ok(())
+}
+
 pub fn import_from_safes_binary(path: &str) -> Result<DB, io::Error> {
     let mut f = File::open(path)?;
 
@@ -123,6 +247,111 @@ pub fn import_from_safes_binary(path: &str) -> Result<DB, io::Error> {
     Ok(DB::new(safes, token_owner))
 }
 
+/// Writes the safes in the format read by `import_from_safes_binary`.
+/// Balances are stored by token owner, so balances in tokens without
+/// a known owner are skipped.
+pub fn write_safes_binary(db: &DB, path: &str) -> Result<(), io::Error> {
+    let mut file = File::create(path)?;
+
+    let mut organizations = vec![];
+    let mut trust_edges = vec![];
+    let mut balances = vec![];
+    for (user, safe) in db.safes() {
+        if safe.organization {
+            organizations.push(*user);
+        }
+        for (send_to, percentage) in &safe.limit_percentage {
+            trust_edges.push((*user, *send_to, *percentage));
+        }
+        for (token, balance) in &safe.balances {
+            if let Some(owner) = db.token_owner().get(token) {
+                balances.push((*user, *owner, *balance));
+            }
+        }
+    }
+
+    let mut addresses = BTreeSet::new();
+    addresses.extend(organizations.iter());
+    addresses.extend(trust_edges.iter().flat_map(|(a, b, _)| [*a, *b]));
+    addresses.extend(balances.iter().flat_map(|(a, b, _)| [*a, *b]));
+    let address_index = write_addresses(&mut file, addresses)?;
+
+    write_u32(&mut file, organizations.len() as u32)?;
+    for org in &organizations {
+        write_address(&mut file, org, &address_index)?;
+    }
+    write_u32(&mut file, trust_edges.len() as u32)?;
+    for (user, send_to, percentage) in &trust_edges {
+        write_address(&mut file, user, &address_index)?;
+        write_address(&mut file, send_to, &address_index)?;
+        write_u8(&mut file, *percentage)?;
+    }
+    write_u32(&mut file, balances.len() as u32)?;
+    for (user, token_owner, balance) in &balances {
+        write_address(&mut file, user, &address_index)?;
+        write_address(&mut file, token_owner, &address_index)?;
+        write_u256(&mut file, balance)?;
+    }
+    Ok(())
+}
+
+/// Skips over a binary address index and returns the number of addresses.
+fn skip_address_index(data: &mut &[u8]) -> Option<u32> {
+    let count = take_u32(data)?;
+    take(data, count as usize * 20)?;
+    Some(count)
+}
+
+/// Returns `Some` if `data` is an address index followed by edges.
+fn is_edges_binary(mut data: &[u8]) -> Option<()> {
+    let data = &mut data;
+    let addresses = skip_address_index(data)?;
+    for _ in 0..take_u32(data)? {
+        for _ in 0..3 {
+            (take_u32(data)? < addresses).then_some(())?;
+        }
+        skip_u256(data)?;
+    }
+    data.is_empty().then_some(())
+}
+
+/// Returns `Some` if `data` has the structure read by `import_from_safes_binary`.
+fn is_safes_binary(mut data: &[u8]) -> Option<()> {
+    let data = &mut data;
+    let addresses = skip_address_index(data)?;
+    let address = |data: &mut &[u8]| (take_u32(data)? < addresses).then_some(());
+    for _ in 0..take_u32(data)? {
+        address(data)?;
+    }
+    for _ in 0..take_u32(data)? {
+        address(data)?;
+        address(data)?;
+        take(data, 1)?;
+    }
+    for _ in 0..take_u32(data)? {
+        address(data)?;
+        address(data)?;
+        skip_u256(data)?;
+    }
+    data.is_empty().then_some(())
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let (head, tail) = (data.get(..len)?, data.get(len..)?);
+    *data = tail;
+    Some(head)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(take(data, 4)?.try_into().unwrap()))
+}
+
+fn skip_u256(data: &mut &[u8]) -> Option<()> {
+    let length = take(data, 1)?[0] as usize;
+    (length <= 32).then_some(())?;
+    take(data, length).map(|_| ())
+}
+
 fn read_address_index(file: &mut File) -> Result<HashMap<u32, Address>, io::Error> {
     let address_count = read_u32(file)?;
     let mut addresses = HashMap::new();
@@ -147,6 +376,13 @@ fn write_address_index(
         addresses.insert(*to);
         addresses.insert(*token);
     }
+    write_addresses(file, addresses)
+}
+
+fn write_addresses(
+    file: &mut File,
+    addresses: BTreeSet<Address>,
+) -> Result<HashMap<Address, u32>, io::Error> {
     write_u32(file, addresses.len() as u32)?;
     let mut index = HashMap::new();
     for (i, addr) in addresses.into_iter().enumerate() {
@@ -1,5 +1,9 @@
+pub mod abi;
 pub mod graph;
+pub mod hash;
 pub mod io;
+pub mod replay;
 pub mod safe_db;
 pub mod server;
+pub mod time_circles;
 pub mod types;
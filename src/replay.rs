@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use crate::io::import_from_safes_binary;
+use crate::safe_db::db::DB;
+use crate::types::{Address, Edge, U256};
+
+/// Offline model of the state of the Circles Hub contract (balances, trust limits
+/// and organizations) that can execute `transferThrough` with the same rules as
+/// the contract, so that transfers can be validated without an RPC node.
+#[derive(Debug, Default, Clone)]
+pub struct HubState {
+    /// Balances indexed by (token owner, holder).
+    balances: HashMap<(Address, Address), U256>,
+    /// Trust limit percentages indexed by (truster, trusted token owner).
+    limits: HashMap<(Address, Address), u8>,
+    /// Users that signed up and thus own a token.
+    users: HashSet<Address>,
+    organizations: HashSet<Address>,
+}
+
+/// Reasons for which the Hub would revert a `transferThrough` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    EmptyPath,
+    TokenDoesNotExist {
+        step: usize,
+        token_owner: Address,
+    },
+    TrustLimitExceeded {
+        step: usize,
+        limit: U256,
+        value: U256,
+    },
+    InsufficientBalance {
+        step: usize,
+        balance: U256,
+        value: U256,
+    },
+    MultipleSenders,
+    MultipleReceivers,
+    NotSentFromTransactionSender {
+        sender: Address,
+    },
+    UnequalAmounts,
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ReplayError::EmptyPath => write!(f, "Path is empty"),
+            ReplayError::TokenDoesNotExist { step, token_owner } => {
+                write!(f, "Step {step}: Token of {token_owner} does not exist")
+            }
+            ReplayError::TrustLimitExceeded { step, limit, value } => write!(
+                f,
+                "Step {step}: Trust limit exceeded (limit {}, value {})",
+                limit.to_decimal(),
+                value.to_decimal()
+            ),
+            ReplayError::InsufficientBalance {
+                step,
+                balance,
+                value,
+            } => write!(
+                f,
+                "Step {step}: Insufficient balance (balance {}, value {})",
+                balance.to_decimal(),
+                value.to_decimal()
+            ),
+            ReplayError::MultipleSenders => write!(f, "Path sends from more than one src"),
+            ReplayError::MultipleReceivers => write!(f, "Path sends to more than one dest"),
+            ReplayError::NotSentFromTransactionSender { sender } => {
+                write!(f, "Path doesn't send from transaction sender {sender}")
+            }
+            ReplayError::UnequalAmounts => write!(f, "Unequal sent and received amounts"),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Net effect of a successful `transferThrough` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferSummary {
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+}
+
+#[derive(Default)]
+struct Validation {
+    sent: U256,
+    received: U256,
+}
+
+impl HubState {
+    /// Loads the Hub state from a snapshot file in the binary safes format.
+    pub fn from_snapshot(path: &str) -> Result<HubState, io::Error> {
+        Ok(HubState::from_db(&import_from_safes_binary(path)?))
+    }
+
+    pub fn from_db(db: &DB) -> HubState {
+        let mut state = HubState::default();
+        for (address, safe) in db.safes() {
+            if safe.organization {
+                state.organizations.insert(*address);
+            } else {
+                state.users.insert(*address);
+                // Users trust themselves when signing up.
+                state.limits.insert((*address, *address), 100);
+            }
+            for (send_to, percentage) in &safe.limit_percentage {
+                state.limits.insert((*send_to, *address), *percentage);
+            }
+            for (token, balance) in &safe.balances {
+                if let Some(owner) = db.token_owner().get(token) {
+                    state.balances.insert((*owner, *address), *balance);
+                }
+            }
+        }
+        state
+    }
+
+    pub fn balance(&self, token_owner: &Address, holder: &Address) -> U256 {
+        *self
+            .balances
+            .get(&(*token_owner, *holder))
+            .unwrap_or(&U256::from(0))
+    }
+
+    /// Returns the trust limit percentage of `truster` for tokens of `token_owner`.
+    pub fn limit(&self, truster: &Address, token_owner: &Address) -> u8 {
+        *self.limits.get(&(*truster, *token_owner)).unwrap_or(&0)
+    }
+
+    /// Mirrors `Hub.checkSendLimit`: how many tokens of `token_owner`
+    /// `src` can currently send to `dest`.
+    pub fn check_send_limit(&self, token_owner: &Address, src: &Address, dest: &Address) -> U256 {
+        let limit = self.limit(dest, token_owner);
+        if limit == 0 {
+            return U256::from(0);
+        }
+        let dest_is_organization = self.organizations.contains(dest);
+        if !self.users.contains(dest) && !dest_is_organization {
+            return U256::from(0);
+        }
+        if !self.users.contains(token_owner) {
+            return U256::from(0);
+        }
+        let src_balance = self.balance(token_owner, src);
+        if token_owner == dest || dest_is_organization {
+            return src_balance;
+        }
+        let dest_balance = self.balance(token_owner, dest);
+        let one_hundred = U256::from(100);
+        // The Hub uses SafeMath, an overflow reverts and nothing can be sent.
+        let Some(max) = self
+            .balance(dest, dest)
+            .checked_mul(U256::from(limit as u128))
+        else {
+            return U256::from(0);
+        };
+        let max = max / one_hundred;
+        if max < dest_balance {
+            return U256::from(0);
+        }
+        let Some(dest_balance_scaled) = dest_balance.checked_mul(U256::from((100 - limit) as u128))
+        else {
+            return U256::from(0);
+        };
+        max - dest_balance_scaled / one_hundred
+    }
+
+    /// Executes `transferThrough` with the given transfers sent by `sender`,
+    /// mirroring the checks of the Hub contract. The state is only modified
+    /// if the call succeeds.
+    pub fn transfer_through(
+        &mut self,
+        sender: &Address,
+        transfers: &[Edge],
+    ) -> Result<TransferSummary, ReplayError> {
+        if transfers.is_empty() {
+            return Err(ReplayError::EmptyPath);
+        }
+        let mut state = self.clone();
+        let mut validation: BTreeMap<Address, Validation> = BTreeMap::new();
+        for (step, transfer) in transfers.iter().enumerate() {
+            let Edge {
+                from,
+                to,
+                token,
+                capacity: value,
+            } = *transfer;
+            if !state.users.contains(&token) {
+                return Err(ReplayError::TokenDoesNotExist {
+                    step,
+                    token_owner: token,
+                });
+            }
+            let limit = state.check_send_limit(&token, &from, &to);
+            if value > limit {
+                return Err(ReplayError::TrustLimitExceeded { step, limit, value });
+            }
+            let balance = state.balance(&token, &from);
+            if value > balance {
+                return Err(ReplayError::InsufficientBalance {
+                    step,
+                    balance,
+                    value,
+                });
+            }
+            state.balances.insert((token, from), balance - value);
+            *state.balances.entry((token, to)).or_default() += value;
+            validation.entry(from).or_default().sent += value;
+            validation.entry(to).or_default().received += value;
+        }
+
+        let mut src: Option<(Address, U256)> = None;
+        let mut dest: Option<(Address, U256)> = None;
+        for (address, v) in &validation {
+            if v.sent > v.received {
+                if src.is_some() {
+                    return Err(ReplayError::MultipleSenders);
+                }
+                if address != sender {
+                    return Err(ReplayError::NotSentFromTransactionSender { sender: *sender });
+                }
+                src = Some((*address, v.sent - v.received));
+            }
+            if v.received > v.sent {
+                if dest.is_some() {
+                    return Err(ReplayError::MultipleReceivers);
+                }
+                dest = Some((*address, v.received - v.sent));
+            }
+        }
+        match (src, dest) {
+            (Some((from, sent)), Some((to, received))) if sent == received => {
+                *self = state;
+                Ok(TransferSummary {
+                    from,
+                    to,
+                    amount: sent,
+                })
+            }
+            _ => Err(ReplayError::UnequalAmounts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Safe;
+
+    fn addresses() -> (Address, Address, Address) {
+        (
+            Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E"),
+            Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37"),
+            Address::from("0x33cEDde51198D1773590311E2A340DC06B24cB37"),
+        )
+    }
+
+    /// a and b have 100 of their own tokens each, b trusts a with 50%.
+    fn state() -> HubState {
+        let (a, b, _) = addresses();
+        let mut safes = BTreeMap::new();
+        safes.insert(
+            a,
+            Safe {
+                token_address: a,
+                balances: [(a, U256::from(100))].into(),
+                limit_percentage: [(b, 50)].into(),
+                organization: false,
+            },
+        );
+        safes.insert(
+            b,
+            Safe {
+                token_address: b,
+                balances: [(b, U256::from(100))].into(),
+                ..Default::default()
+            },
+        );
+        HubState::from_db(&DB::new(safes, [(a, a), (b, b)].into()))
+    }
+
+    fn transfer(from: Address, to: Address, token: Address, value: u128) -> Edge {
+        Edge {
+            from,
+            to,
+            token,
+            capacity: U256::from(value),
+        }
+    }
+
+    #[test]
+    fn send_limit() {
+        let (a, b, c) = addresses();
+        let state = state();
+        assert_eq!(state.check_send_limit(&a, &a, &b), U256::from(50));
+        assert_eq!(state.check_send_limit(&b, &b, &a), U256::from(0));
+        assert_eq!(state.check_send_limit(&a, &a, &c), U256::from(0));
+    }
+
+    #[test]
+    fn valid_transfer() {
+        let (a, b, _) = addresses();
+        let mut state = state();
+        let summary = state
+            .transfer_through(&a, &[transfer(a, b, a, 30)])
+            .unwrap();
+        assert_eq!(
+            summary,
+            TransferSummary {
+                from: a,
+                to: b,
+                amount: U256::from(30)
+            }
+        );
+        assert_eq!(state.balance(&a, &b), U256::from(30));
+        assert_eq!(state.balance(&a, &a), U256::from(70));
+        // Now b holds 30 of a's tokens, which reduces the limit.
+        assert_eq!(state.check_send_limit(&a, &a, &b), U256::from(35));
+        // Returning tokens to the owner is always possible.
+        assert!(state.transfer_through(&b, &[transfer(b, a, a, 30)]).is_ok());
+    }
+
+    #[test]
+    fn invalid_transfers() {
+        let (a, b, c) = addresses();
+        let mut state = state();
+        assert_eq!(
+            state.transfer_through(&a, &[transfer(a, b, a, 60)]),
+            Err(ReplayError::TrustLimitExceeded {
+                step: 0,
+                limit: U256::from(50),
+                value: U256::from(60)
+            })
+        );
+        assert_eq!(
+            state.transfer_through(&b, &[transfer(a, b, a, 10)]),
+            Err(ReplayError::NotSentFromTransactionSender { sender: b })
+        );
+        assert_eq!(
+            state.transfer_through(&a, &[transfer(a, b, c, 10)]),
+            Err(ReplayError::TokenDoesNotExist {
+                step: 0,
+                token_owner: c
+            })
+        );
+        assert_eq!(state.transfer_through(&a, &[]), Err(ReplayError::EmptyPath));
+        // Failed calls do not modify the state.
+        assert_eq!(state.balance(&a, &a), U256::from(100));
+    }
+}
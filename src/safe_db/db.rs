@@ -11,7 +11,7 @@ pub struct DB {
 
 impl DB {
     pub fn new(safes: BTreeMap<Address, Safe>, token_owner: BTreeMap<Address, Address>) -> DB {
-        println!("{} safes, {} tokens", safes.len(), token_owner.len());
+        log::info!("{} safes, {} tokens", safes.len(), token_owner.len());
         let mut db = DB {
             safes,
             token_owner,
@@ -21,6 +21,14 @@ impl DB {
         db
     }
 
+    pub fn safes(&self) -> &BTreeMap<Address, Safe> {
+        &self.safes
+    }
+
+    pub fn token_owner(&self) -> &BTreeMap<Address, Address> {
+        &self.token_owner
+    }
+
     pub fn edges(&self) -> &EdgeDB {
         &self.edges
     }
@@ -28,40 +36,64 @@ impl DB {
     fn compute_edges(&mut self) {
         let mut edges = vec![];
         for (user, safe) in &self.safes {
-            // trust connections
-            for (send_to, percentage) in &safe.limit_percentage {
-                if *user == *send_to {
-                    continue;
-                }
-                if let Some(receiver_safe) = self.safes.get(send_to) {
-                    // TODO should return "limited or not"
-                    // edge should contain token balance and transfer limit (which can be unlimited)
-                    let limit = safe.trust_transfer_limit(receiver_safe, *percentage);
-                    if limit != U256::from(0) {
-                        edges.push(Edge {
-                            from: *user,
-                            to: *send_to,
-                            token: *user,
-                            capacity: limit,
-                        })
-                    }
-                }
+            edges.extend(safe_edges(self, user, safe));
+        }
+        self.edges = EdgeDB::new(edges)
+    }
+}
+
+/// Read access to safes and token owners, so that edges can be computed both
+/// for a `DB` and for hypothetical changes on top of it.
+pub(crate) trait SafeSource {
+    fn safe(&self, address: &Address) -> Option<&Safe>;
+    fn token_owner(&self, token: &Address) -> Option<Address>;
+}
+
+impl SafeSource for DB {
+    fn safe(&self, address: &Address) -> Option<&Safe> {
+        self.safes.get(address)
+    }
+
+    fn token_owner(&self, token: &Address) -> Option<Address> {
+        self.token_owner.get(token).copied()
+    }
+}
+
+/// Computes the outgoing edges of `user`.
+pub(crate) fn safe_edges(source: &impl SafeSource, user: &Address, safe: &Safe) -> Vec<Edge> {
+    let mut edges = vec![];
+    // trust connections
+    for (send_to, percentage) in &safe.limit_percentage {
+        if *user == *send_to {
+            continue;
+        }
+        if let Some(receiver_safe) = source.safe(send_to) {
+            // TODO should return "limited or not"
+            // edge should contain token balance and transfer limit (which can be unlimited)
+            let limit = safe.trust_transfer_limit(receiver_safe, *percentage);
+            if limit != U256::from(0) {
+                edges.push(Edge {
+                    from: *user,
+                    to: *send_to,
+                    token: *user,
+                    capacity: limit,
+                })
             }
-            // send tokens back to owner
-            for (token, balance) in &safe.balances {
-                if let Some(owner) = self.token_owner.get(token) {
-                    if *user != *owner && *balance != U256::from(0) {
-                        edges.push(Edge {
-                            from: *user,
-                            to: *owner,
-                            token: *owner,
-                            // TODO capacity should be only limited by own balance.
-                            capacity: *balance,
-                        })
-                    }
-                }
+        }
+    }
+    // send tokens back to owner
+    for (token, balance) in &safe.balances {
+        if let Some(owner) = source.token_owner(token) {
+            if *user != owner && *balance != U256::from(0) {
+                edges.push(Edge {
+                    from: *user,
+                    to: owner,
+                    token: owner,
+                    // TODO capacity should be only limited by own balance.
+                    capacity: *balance,
+                })
             }
         }
-        self.edges = EdgeDB::new(edges)
     }
+    edges
 }
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use crate::graph;
+use crate::replay::HubState;
+use crate::safe_db::db::DB;
+use crate::types::{Address, AddressBook, U256};
+
+/// How the tokens of one owner are distributed among their holders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenDistribution {
+    pub token_owner: Address,
+    /// Sum of all balances.
+    pub supply: U256,
+    /// Number of accounts with a non-zero balance.
+    pub holders: usize,
+    /// Fraction of the supply held by the owner.
+    pub owner_share: f64,
+    /// Fraction of the supply held by the largest holder.
+    pub top_holder_share: f64,
+    /// Herfindahl-Hirschman index of the balances (1 if one account holds everything).
+    pub concentration: f64,
+    /// Sum of the balances that cannot be sent to anyone but the owner.
+    pub stuck: U256,
+}
+
+/// A balance that no trust edge can move, apart from sending it back to the owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StuckBalance {
+    pub holder: Address,
+    pub token_owner: Address,
+    pub balance: U256,
+}
+
+/// An account whose balance limits the flow between many pairs of other accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BottleneckAccount {
+    pub address: Address,
+    /// Number of sampled pairs where a balance of the account is part of the minimum cut.
+    pub occurrences: usize,
+    /// Sum of the capacities of these balance constraints.
+    pub capacity: U256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidityReport {
+    /// One entry per token with a non-zero supply, ordered by token owner.
+    pub tokens: Vec<TokenDistribution>,
+    /// Ordered by decreasing balance.
+    pub stuck_balances: Vec<StuckBalance>,
+    /// Ordered by decreasing number of occurrences.
+    pub bottleneck_accounts: Vec<BottleneckAccount>,
+}
+
+impl LiquidityReport {
+    /// Computes the distribution and the stuck balances of all tokens, and the accounts
+    /// whose balances appear in the minimum cuts between the given pairs of accounts.
+    pub fn new(db: &DB, pairs: &[(Address, Address)]) -> LiquidityReport {
+        let hub = HubState::from_db(db);
+        let mut holders: BTreeMap<Address, Vec<(Address, U256)>> = BTreeMap::new();
+        for (holder, safe) in db.safes() {
+            for (token, balance) in &safe.balances {
+                if let Some(owner) = db.token_owner().get(token) {
+                    if *balance != U256::from(0) {
+                        holders.entry(*owner).or_default().push((*holder, *balance));
+                    }
+                }
+            }
+        }
+
+        let mut tokens = vec![];
+        let mut stuck_balances = vec![];
+        for (owner, balances) in holders {
+            let supply = balances
+                .iter()
+                .fold(U256::from(0), |sum, (_, b)| sum.saturating_add(*b));
+            let share = |balance: U256| balance.to_f64() / supply.to_f64();
+            let mut stuck = U256::from(0);
+            for (holder, balance) in &balances {
+                if is_stuck(db, &hub, &owner, holder) {
+                    stuck = stuck.saturating_add(*balance);
+                    stuck_balances.push(StuckBalance {
+                        holder: *holder,
+                        token_owner: owner,
+                        balance: *balance,
+                    });
+                }
+            }
+            tokens.push(TokenDistribution {
+                token_owner: owner,
+                supply,
+                holders: balances.len(),
+                owner_share: share(hub.balance(&owner, &owner)),
+                top_holder_share: share(balances.iter().map(|(_, b)| *b).max().unwrap()),
+                concentration: balances.iter().map(|(_, b)| share(*b).powi(2)).sum(),
+                stuck,
+            });
+        }
+        stuck_balances.sort_by_key(|s| std::cmp::Reverse(s.balance));
+
+        LiquidityReport {
+            tokens,
+            stuck_balances,
+            bottleneck_accounts: bottleneck_accounts(db, pairs),
+        }
+    }
+}
+
+/// Returns true if `holder` cannot send tokens of `token_owner` to anyone except the owner.
+fn is_stuck(db: &DB, hub: &HubState, token_owner: &Address, holder: &Address) -> bool {
+    if holder == token_owner {
+        return false;
+    }
+    let Some(owner_safe) = db.safes().get(token_owner) else {
+        return true;
+    };
+    // Everyone who trusts the owner could receive the tokens.
+    owner_safe
+        .limit_percentage
+        .keys()
+        .filter(|dest| *dest != holder && *dest != token_owner)
+        .all(|dest| hub.check_send_limit(token_owner, holder, dest) == U256::from(0))
+}
+
+fn bottleneck_accounts(db: &DB, pairs: &[(Address, Address)]) -> Vec<BottleneckAccount> {
+    let mut accounts: HashMap<Address, BottleneckAccount> = HashMap::new();
+    for (source, sink) in pairs {
+        let explanation = graph::explain_flow(
+            source,
+            sink,
+            db.edges(),
+            U256::MAX,
+            None,
+            &AddressBook::default(),
+        );
+        // The balance of the source always limits the flow, we are only interested
+        // in intermediate accounts.
+        for bottleneck in explanation.sender_balance {
+            let Some(address) = bottleneck.from.filter(|a| a != source) else {
+                continue;
+            };
+            let account = accounts.entry(address).or_insert(BottleneckAccount {
+                address,
+                occurrences: 0,
+                capacity: U256::from(0),
+            });
+            account.occurrences += 1;
+            account.capacity = account.capacity.saturating_add(bottleneck.capacity);
+        }
+    }
+    let mut accounts = accounts.into_values().collect::<Vec<_>>();
+    accounts.sort_by(|a, b| {
+        (b.occurrences, b.capacity, a.address).cmp(&(a.occurrences, a.capacity, b.address))
+    });
+    accounts
+}
+
+/// Returns `count` pairs of distinct addresses, spread evenly over `addresses`.
+pub fn sample_pairs(addresses: &[Address], count: usize) -> Vec<(Address, Address)> {
+    let n = addresses.len();
+    if n < 2 {
+        return vec![];
+    }
+    (0..count)
+        .map(|i| {
+            let source = i * 7919 % n;
+            // Offset between 1 and n - 1, so that source and sink differ.
+            let offset = 1 + (i * 104729 + count) % (n - 1);
+            (addresses[source], addresses[(source + offset) % n])
+        })
+        .collect()
+}
+
+impl Display for LiquidityReport {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let mut by_concentration = self.tokens.iter().collect::<Vec<_>>();
+        by_concentration.sort_by(|a, b| b.concentration.total_cmp(&a.concentration));
+        writeln!(f, "Tokens: {}", self.tokens.len())?;
+        writeln!(
+            f,
+            "Most concentrated tokens (owner, holders, owner share, top holder share, concentration):"
+        )?;
+        for t in by_concentration.iter().filter(|t| t.holders > 1).take(10) {
+            writeln!(
+                f,
+                "    {} {} {:.3} {:.3} {:.3}",
+                t.token_owner, t.holders, t.owner_share, t.top_holder_share, t.concentration
+            )?;
+        }
+        let stuck = self
+            .stuck_balances
+            .iter()
+            .fold(U256::from(0), |sum, s| sum.saturating_add(s.balance));
+        writeln!(
+            f,
+            "Stuck balances: {} ({} CRC)",
+            self.stuck_balances.len(),
+            stuck.to_crc()
+        )?;
+        for s in self.stuck_balances.iter().take(10) {
+            writeln!(
+                f,
+                "    {} holds {} CRC of {}",
+                s.holder,
+                s.balance.to_crc(),
+                s.token_owner
+            )?;
+        }
+        writeln!(
+            f,
+            "Accounts whose balances limit the most flows (address, occurrences, capacity):"
+        )?;
+        for a in self.bottleneck_accounts.iter().take(10) {
+            writeln!(
+                f,
+                "    {} {} {} CRC",
+                a.address,
+                a.occurrences,
+                a.capacity.to_crc()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Safe;
+
+    fn safe(
+        address: Address,
+        balances: Vec<(Address, u128)>,
+        trusted_by: Vec<Address>,
+    ) -> (Address, Safe) {
+        let safe = Safe {
+            token_address: address,
+            balances: balances
+                .into_iter()
+                .map(|(token, amount)| (token, crc(amount)))
+                .collect(),
+            limit_percentage: trusted_by.into_iter().map(|t| (t, 50)).collect(),
+            organization: false,
+        };
+        (address, safe)
+    }
+
+    fn crc(amount: u128) -> U256 {
+        U256::from(amount * 1000000000000000000)
+    }
+
+    fn db(safes: Vec<(Address, Safe)>) -> DB {
+        let token_owner = safes.iter().map(|(a, _)| (*a, *a)).collect();
+        DB::new(safes.into_iter().collect(), token_owner)
+    }
+
+    #[test]
+    fn distribution() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        // b and c trust a, d trusts b.
+        let db = db(vec![
+            safe(a, vec![(a, 60), (c, 5)], vec![b, c]),
+            safe(b, vec![(b, 100), (a, 20)], vec![d]),
+            safe(c, vec![(c, 100), (a, 20), (b, 10)], vec![]),
+            safe(d, vec![(d, 100), (b, 90)], vec![]),
+        ]);
+        let report = LiquidityReport::new(&db, &[]);
+        let token_a = &report.tokens[0];
+        assert_eq!(token_a.token_owner, a);
+        assert_eq!(token_a.supply, crc(100));
+        assert_eq!(token_a.holders, 3);
+        assert_eq!(token_a.owner_share, 0.6);
+        assert_eq!(token_a.top_holder_share, 0.6);
+        assert!((token_a.concentration - 0.44).abs() < 1e-9);
+        // Tokens of a can be sent between b and c. Tokens of b can only be sent to d,
+        // which already holds more than it accepts, and nobody trusts c.
+        assert_eq!(token_a.stuck, U256::from(0));
+        assert_eq!(report.tokens[1].stuck, crc(100));
+        assert_eq!(
+            report.stuck_balances,
+            vec![
+                StuckBalance {
+                    holder: d,
+                    token_owner: b,
+                    balance: crc(90),
+                },
+                StuckBalance {
+                    holder: c,
+                    token_owner: b,
+                    balance: crc(10),
+                },
+                StuckBalance {
+                    holder: a,
+                    token_owner: c,
+                    balance: crc(5),
+                },
+            ]
+        );
+        assert!(report.bottleneck_accounts.is_empty());
+    }
+
+    #[test]
+    fn bottlenecks() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        // a can send 15 to b directly and 15 via c, where the flow is limited by the
+        // balance of c, the intermediate account.
+        let db = db(vec![
+            safe(a, vec![(a, 100)], vec![b, c]),
+            safe(b, vec![(b, 30)], vec![d]),
+            safe(c, vec![(c, 100)], vec![b]),
+            safe(d, vec![(d, 100)], vec![]),
+        ]);
+        let report = LiquidityReport::new(&db, &[(a, d), (c, d)]);
+        assert!(report.stuck_balances.is_empty());
+        assert_eq!(
+            report.bottleneck_accounts,
+            vec![BottleneckAccount {
+                address: c,
+                occurrences: 1,
+                capacity: crc(15),
+            }]
+        );
+    }
+
+    #[test]
+    fn pairs() {
+        let addresses = [1, 2, 3].map(|i| Address::from([i; 20]));
+        let pairs = sample_pairs(&addresses, 10);
+        assert_eq!(pairs.len(), 10);
+        assert!(pairs.iter().all(|(a, b)| a != b));
+        assert!(sample_pairs(&addresses[..1], 10).is_empty());
+    }
+}
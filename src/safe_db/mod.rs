@@ -1,2 +1,4 @@
 pub mod db;
+pub mod liquidity;
 pub mod safes_json;
+pub mod what_if;
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::safe_db::db::{safe_edges, SafeSource, DB};
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, Safe, U256};
+
+/// A hypothetical change of the state of a safe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SafeChange {
+    /// `truster` trusts `trusted` with the given limit percentage, i.e. accepts tokens
+    /// of `trusted`. A percentage of zero removes the trust.
+    Trust {
+        truster: Address,
+        trusted: Address,
+        percentage: u8,
+    },
+    /// Sets the balance of `holder` in tokens of `token_owner`.
+    Balance {
+        holder: Address,
+        token_owner: Address,
+        amount: U256,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WhatIfError {
+    InvalidPercentage(u8),
+}
+
+impl Display for WhatIfError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            WhatIfError::InvalidPercentage(percentage) => {
+                write!(
+                    f,
+                    "Invalid trust percentage {percentage}, expected at most 100"
+                )
+            }
+        }
+    }
+}
+
+impl Error for WhatIfError {}
+
+/// Changed safes on top of a `DB`, which itself is not modified.
+struct Overlay<'a> {
+    db: &'a DB,
+    safes: BTreeMap<Address, Safe>,
+    token_owner: BTreeMap<Address, Address>,
+}
+
+impl SafeSource for Overlay<'_> {
+    fn safe(&self, address: &Address) -> Option<&Safe> {
+        self.safes.get(address).or_else(|| self.db.safe(address))
+    }
+
+    fn token_owner(&self, token: &Address) -> Option<Address> {
+        self.token_owner
+            .get(token)
+            .copied()
+            .or_else(|| SafeSource::token_owner(self.db, token))
+    }
+}
+
+impl Overlay<'_> {
+    /// Returns the safe to modify, signing up `address` if it does not have a safe yet.
+    fn safe_mut(&mut self, address: &Address) -> &mut Safe {
+        let db = self.db;
+        self.safes.entry(*address).or_insert_with(|| {
+            db.safe(address).cloned().unwrap_or_else(|| Safe {
+                token_address: *address,
+                ..Default::default()
+            })
+        })
+    }
+
+    /// The address of the token owned by `owner`.
+    fn token_of(&mut self, owner: &Address) -> Address {
+        let token = self.safe_mut(owner).token_address;
+        if self.token_owner(&token).is_none() {
+            self.token_owner.insert(token, *owner);
+        }
+        token
+    }
+}
+
+/// Returns the edges of `db` after applying `changes` in order, recomputing only the
+/// outgoing edges of the safes that are affected by the changes.
+pub fn apply_changes(db: &DB, changes: &[SafeChange]) -> Result<EdgeDB, WhatIfError> {
+    let mut overlay = Overlay {
+        db,
+        safes: BTreeMap::new(),
+        token_owner: BTreeMap::new(),
+    };
+    // Addresses whose outgoing edges have to be recomputed.
+    let mut affected = BTreeSet::new();
+    for change in changes {
+        match *change {
+            SafeChange::Trust {
+                truster,
+                trusted,
+                percentage,
+            } => {
+                if percentage > 100 {
+                    return Err(WhatIfError::InvalidPercentage(percentage));
+                }
+                overlay.safe_mut(&truster);
+                let limits = &mut overlay.safe_mut(&trusted).limit_percentage;
+                if percentage == 0 {
+                    limits.remove(&truster);
+                } else {
+                    limits.insert(truster, percentage);
+                }
+                affected.insert(trusted);
+            }
+            SafeChange::Balance {
+                holder,
+                token_owner,
+                amount,
+            } => {
+                let token = overlay.token_of(&token_owner);
+                let balances = &mut overlay.safe_mut(&holder).balances;
+                if amount == U256::from(0) {
+                    balances.remove(&token);
+                } else {
+                    balances.insert(token, amount);
+                }
+                // The balance limits the edge back to the owner and what the owner can
+                // send to the holder.
+                affected.extend([holder, token_owner]);
+                if holder == token_owner {
+                    // The own balance limits what everyone who is trusted by the holder
+                    // can send to them.
+                    affected.extend(
+                        db.safes()
+                            .keys()
+                            .chain(overlay.safes.keys())
+                            .filter(|user| {
+                                overlay
+                                    .safe(user)
+                                    .is_some_and(|s| s.limit_percentage.contains_key(&holder))
+                            }),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut edges = db
+        .edges()
+        .edges()
+        .iter()
+        .filter(|e| !affected.contains(&e.from))
+        .copied()
+        .collect::<Vec<_>>();
+    for user in &affected {
+        if let Some(safe) = overlay.safe(user) {
+            edges.extend(safe_edges(&overlay, user, safe));
+        }
+    }
+    Ok(EdgeDB::new(edges))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn crc(amount: u128) -> U256 {
+        U256::from(amount * 1000000000000000000)
+    }
+
+    /// A safe as (address, balances in CRC per token owner, limit percentages).
+    type TestSafe = (Address, Vec<(Address, u128)>, Vec<(Address, u8)>);
+
+    fn db(safes: Vec<TestSafe>) -> DB {
+        let token_owner = safes.iter().map(|(a, _, _)| (*a, *a)).collect();
+        let safes = safes
+            .into_iter()
+            .map(|(address, balances, limits)| {
+                let safe = Safe {
+                    token_address: address,
+                    balances: balances.into_iter().map(|(t, b)| (t, crc(b))).collect(),
+                    limit_percentage: limits.into_iter().collect(),
+                    organization: false,
+                };
+                (address, safe)
+            })
+            .collect();
+        DB::new(safes, token_owner)
+    }
+
+    fn sorted(edges: &EdgeDB) -> Vec<crate::types::Edge> {
+        let mut edges = edges.edges().clone();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn overlay_matches_rebuilt_db() {
+        let [a, b, c, d] = [1, 2, 3, 4].map(|i| Address::from([i; 20]));
+        // b trusts a, c trusts b.
+        let base = db(vec![
+            (a, vec![(a, 100)], vec![(b, 50)]),
+            (b, vec![(b, 100), (a, 10)], vec![(c, 50)]),
+            (c, vec![(c, 20)], vec![]),
+        ]);
+        let changes = [
+            SafeChange::Trust {
+                truster: c,
+                trusted: a,
+                percentage: 30,
+            },
+            SafeChange::Trust {
+                truster: c,
+                trusted: b,
+                percentage: 0,
+            },
+            SafeChange::Balance {
+                holder: c,
+                token_owner: c,
+                amount: crc(200),
+            },
+            SafeChange::Balance {
+                holder: d,
+                token_owner: a,
+                amount: crc(5),
+            },
+        ];
+        let overlay = apply_changes(&base, &changes).unwrap();
+        let expected = db(vec![
+            (a, vec![(a, 100)], vec![(b, 50), (c, 30)]),
+            (b, vec![(b, 100), (a, 10)], vec![]),
+            (c, vec![(c, 200)], vec![]),
+            (d, vec![(a, 5)], vec![]),
+        ]);
+        assert_eq!(sorted(&overlay), sorted(expected.edges()));
+        // The base is not modified.
+        assert_eq!(base.safes()[&b].limit_percentage.len(), 1);
+
+        assert_eq!(
+            apply_changes(
+                &base,
+                &[SafeChange::Trust {
+                    truster: c,
+                    trusted: a,
+                    percentage: 101
+                }]
+            )
+            .unwrap_err(),
+            WhatIfError::InvalidPercentage(101)
+        );
+    }
+}
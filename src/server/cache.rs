@@ -0,0 +1,220 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use crate::types::{Address, Edge, U256};
+
+/// Parameters of a flow computation that determine its result.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CacheKey {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub max_distance: Option<u64>,
+    pub max_transfers: Option<u64>,
+}
+
+struct CacheEntry {
+    /// Graph version the result is valid for.
+    version: u64,
+    flow: U256,
+    transfers: Vec<Edge>,
+    /// Tokens used by the transfers. Any capacity in the flow network
+    /// an edge contributes to is specific to the token of that edge.
+    tokens: BTreeSet<Address>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Logical clock for least-recently-used eviction.
+    clock: u64,
+}
+
+/// Least-recently-used cache of flow computation results.
+pub struct FlowCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl FlowCache {
+    pub fn new(capacity: usize) -> FlowCache {
+        FlowCache {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Returns the cached flow and transfers if there is a result for `key`
+    /// computed on the graph with the given version.
+    pub fn get(&self, key: &CacheKey, version: u64) -> Option<(U256, Vec<Edge>)> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        match entries.entries.get_mut(key) {
+            Some(entry) if entry.version == version => {
+                entry.last_used = clock;
+                Some((entry.flow, entry.transfers.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, key: CacheKey, version: u64, flow: U256, transfers: Vec<Edge>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        if entries.entries.len() >= self.capacity && !entries.entries.contains_key(&key) {
+            let least_recently_used = entries
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = least_recently_used {
+                entries.entries.remove(&lru);
+            }
+        }
+        let tokens = transfers.iter().map(|e| e.token).collect();
+        let last_used = entries.clock;
+        entries.entries.insert(
+            key,
+            CacheEntry {
+                version,
+                flow,
+                transfers,
+                tokens,
+                last_used,
+            },
+        );
+    }
+
+    /// Removes all entries, to be called when the graph is replaced.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().entries.clear();
+    }
+
+    /// Called when the graph with version `old_version` was changed into `new_version`
+    /// by applying `updates`. Entries that are still valid are moved to the new version,
+    /// all others are removed.
+    ///
+    /// An entry stays valid if it achieved the requested value (so no capacity increase
+    /// can improve it) and none of the updated edges uses one of its tokens (so none
+    /// of the capacities it relies on changed).
+    pub fn edges_updated(&self, old_version: u64, new_version: u64, updates: &[Edge]) {
+        let updated_tokens = updates.iter().map(|e| e.token).collect::<BTreeSet<_>>();
+        self.entries.lock().unwrap().entries.retain(|key, entry| {
+            let valid = entry.version == old_version
+                && entry.flow == key.value
+                && entry.tokens.is_disjoint(&updated_tokens);
+            if valid {
+                entry.version = new_version;
+            }
+            valid
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addresses() -> (Address, Address, Address) {
+        (
+            Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E"),
+            Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37"),
+            Address::from("0x33cEDde51198D1773590311E2A340DC06B24cB37"),
+        )
+    }
+
+    fn key(from: Address, to: Address, value: U256) -> CacheKey {
+        CacheKey {
+            from,
+            to,
+            value,
+            max_distance: None,
+            max_transfers: None,
+        }
+    }
+
+    fn transfer(from: Address, to: Address, token: Address, capacity: U256) -> Edge {
+        Edge {
+            from,
+            to,
+            token,
+            capacity,
+        }
+    }
+
+    #[test]
+    fn lookup_requires_version() {
+        let (a, b, _) = addresses();
+        let cache = FlowCache::new(10);
+        let k = key(a, b, U256::from(5));
+        cache.insert(k.clone(), 1, U256::from(5), vec![]);
+        assert_eq!(cache.get(&k, 1), Some((U256::from(5), vec![])));
+        assert_eq!(cache.get(&k, 2), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let (a, b, c) = addresses();
+        let cache = FlowCache::new(2);
+        let (k1, k2, k3) = (
+            key(a, b, U256::from(1)),
+            key(a, c, U256::from(1)),
+            key(b, c, U256::from(1)),
+        );
+        cache.insert(k1.clone(), 1, U256::from(1), vec![]);
+        cache.insert(k2.clone(), 1, U256::from(1), vec![]);
+        cache.get(&k1, 1);
+        cache.insert(k3.clone(), 1, U256::from(1), vec![]);
+        assert!(cache.get(&k1, 1).is_some());
+        assert!(cache.get(&k2, 1).is_none());
+        assert!(cache.get(&k3, 1).is_some());
+    }
+
+    #[test]
+    fn update_invalidates_touched_entries() {
+        let (a, b, c) = addresses();
+        let cache = FlowCache::new(10);
+        // Saturated, uses token a.
+        let k1 = key(a, b, U256::from(5));
+        cache.insert(
+            k1.clone(),
+            1,
+            U256::from(5),
+            vec![transfer(a, b, a, U256::from(5))],
+        );
+        // Saturated, uses token b.
+        let k2 = key(b, c, U256::from(5));
+        cache.insert(
+            k2.clone(),
+            1,
+            U256::from(5),
+            vec![transfer(b, c, b, U256::from(5))],
+        );
+        // Not saturated, could profit from any increase.
+        let k3 = key(c, a, U256::from(7));
+        cache.insert(
+            k3.clone(),
+            1,
+            U256::from(5),
+            vec![transfer(c, a, c, U256::from(5))],
+        );
+        cache.edges_updated(1, 2, &[transfer(c, b, a, U256::from(1))]);
+        assert!(cache.get(&k1, 2).is_none());
+        assert!(cache.get(&k2, 2).is_some());
+        assert!(cache.get(&k3, 2).is_none());
+        assert_eq!(cache.len(), 1);
+    }
+}
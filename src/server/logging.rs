@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+thread_local! {
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Logger that writes one JSON object per line to stdout.
+/// Records logged while a request is processed carry its request id.
+pub struct JsonLogger {
+    level: LevelFilter,
+}
+
+impl JsonLogger {
+    /// Installs the logger as the global logger.
+    pub fn init(level: LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(JsonLogger { level }))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let mut entry = serde_json::json!({
+            "timestamp": timestamp,
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        if let Some(request_id) = current_request_id() {
+            entry["request_id"] = request_id.into();
+        }
+        let _ = writeln!(std::io::stdout().lock(), "{entry}");
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Returns the id of the request processed by the current thread, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.with(|id| id.borrow().clone())
+}
+
+/// Associates all log records of the current thread with a request id
+/// until the returned guard is dropped.
+/// If `request_id` is `None`, a new id is generated.
+pub fn enter_request(request_id: Option<String>) -> RequestIdGuard {
+    let request_id =
+        request_id.unwrap_or_else(|| NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed).to_string());
+    REQUEST_ID.with(|id| *id.borrow_mut() = Some(request_id));
+    RequestIdGuard {}
+}
+
+pub struct RequestIdGuard {}
+
+impl Drop for RequestIdGuard {
+    fn drop(&mut self) {
+        REQUEST_ID.with(|id| *id.borrow_mut() = None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_id_scope() {
+        assert_eq!(current_request_id(), None);
+        {
+            let _guard = enter_request(Some("abc".to_string()));
+            assert_eq!(current_request_id(), Some("abc".to_string()));
+        }
+        assert_eq!(current_request_id(), None);
+        let _guard = enter_request(None);
+        assert!(current_request_id().is_some());
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::graph::FlowStats;
+use crate::types::U256;
+
+/// Upper bounds (in seconds) of the request latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 60.0];
+
+/// Methods that are reported with their own label, all others are
+/// reported as "unknown" to keep the number of time series bounded.
+const KNOWN_METHODS: [&str; 8] = [
+    "compute_transfer",
+    "explain_flow",
+    "find_paths",
+    "load_edges_binary",
+    "load_edges_csv",
+    "load_safes_binary",
+    "update_edges",
+    "what_if",
+];
+
+#[derive(Default)]
+struct MethodStats {
+    count: u64,
+    errors: u64,
+    latency_sum: f64,
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+/// Properties of the currently loaded graph, reported as gauges.
+pub struct GraphInfo {
+    pub edge_count: usize,
+    pub cache_entries: usize,
+    pub version: u64,
+    pub block_number: Option<u64>,
+}
+
+/// Server metrics, rendered in the Prometheus text exposition format.
+#[derive(Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<&'static str, MethodStats>>,
+    queue_depth: AtomicUsize,
+    rejected_connections: AtomicU64,
+    rate_limited_requests: AtomicU64,
+    flow_computations: AtomicU64,
+    augmenting_paths: AtomicU64,
+    transfer_reduction_loss: Mutex<U256>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_request(&self, method: &str, latency: Duration, success: bool) {
+        let method = KNOWN_METHODS
+            .into_iter()
+            .find(|m| *m == method)
+            .unwrap_or("unknown");
+        let latency = latency.as_secs_f64();
+        let mut requests = self.requests.lock().unwrap();
+        let stats = requests.entry(method).or_default();
+        stats.count += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        stats.latency_sum += latency;
+        for (bucket, bound) in stats.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if latency <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    pub fn record_flow(&self, stats: &FlowStats) {
+        self.flow_computations.fetch_add(1, Ordering::Relaxed);
+        self.augmenting_paths
+            .fetch_add(stats.augmenting_paths, Ordering::Relaxed);
+        *self.transfer_reduction_loss.lock().unwrap() += stats.transfer_reduction_loss;
+    }
+
+    pub fn connection_queued(&self) {
+        self.queue_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_dequeued(&self) {
+        self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn request_rate_limited(&self) {
+        self.rate_limited_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, graph: &GraphInfo) -> String {
+        let mut out = String::new();
+        writeln!(out, "# TYPE pathfinder_requests_total counter").expect("");
+        writeln!(out, "# TYPE pathfinder_request_errors_total counter").expect("");
+        writeln!(out, "# TYPE pathfinder_request_duration_seconds histogram").expect("");
+        for (method, stats) in self.requests.lock().unwrap().iter() {
+            writeln!(
+                out,
+                "pathfinder_requests_total{{method=\"{method}\"}} {}",
+                stats.count
+            )
+            .expect("");
+            writeln!(
+                out,
+                "pathfinder_request_errors_total{{method=\"{method}\"}} {}",
+                stats.errors
+            )
+            .expect("");
+            for (count, bound) in stats.latency_buckets.iter().zip(LATENCY_BUCKETS) {
+                writeln!(
+                    out,
+                    "pathfinder_request_duration_seconds_bucket{{method=\"{method}\",le=\"{bound}\"}} {count}"
+                )
+                .expect("");
+            }
+            writeln!(
+                out,
+                "pathfinder_request_duration_seconds_bucket{{method=\"{method}\",le=\"+Inf\"}} {}",
+                stats.count
+            )
+            .expect("");
+            writeln!(
+                out,
+                "pathfinder_request_duration_seconds_sum{{method=\"{method}\"}} {}",
+                stats.latency_sum
+            )
+            .expect("");
+            writeln!(
+                out,
+                "pathfinder_request_duration_seconds_count{{method=\"{method}\"}} {}",
+                stats.count
+            )
+            .expect("");
+        }
+        let gauges = [
+            (
+                "pathfinder_queue_depth",
+                "gauge",
+                self.queue_depth.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "pathfinder_rejected_connections_total",
+                "counter",
+                self.rejected_connections
+                    .load(Ordering::Relaxed)
+                    .to_string(),
+            ),
+            (
+                "pathfinder_rate_limited_requests_total",
+                "counter",
+                self.rate_limited_requests
+                    .load(Ordering::Relaxed)
+                    .to_string(),
+            ),
+            (
+                "pathfinder_flow_computations_total",
+                "counter",
+                self.flow_computations.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "pathfinder_augmenting_paths_total",
+                "counter",
+                self.augmenting_paths.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "pathfinder_transfer_reduction_loss_total",
+                "counter",
+                self.transfer_reduction_loss.lock().unwrap().to_decimal(),
+            ),
+            (
+                "pathfinder_cache_hits_total",
+                "counter",
+                self.cache_hits.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "pathfinder_cache_misses_total",
+                "counter",
+                self.cache_misses.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "pathfinder_cache_entries",
+                "gauge",
+                graph.cache_entries.to_string(),
+            ),
+            (
+                "pathfinder_edge_count",
+                "gauge",
+                graph.edge_count.to_string(),
+            ),
+            (
+                "pathfinder_graph_version",
+                "gauge",
+                graph.version.to_string(),
+            ),
+        ];
+        for (name, kind, value) in gauges {
+            writeln!(out, "# TYPE {name} {kind}").expect("");
+            writeln!(out, "{name} {value}").expect("");
+        }
+        if let Some(block_number) = graph.block_number {
+            writeln!(out, "# TYPE pathfinder_graph_block_number gauge").expect("");
+            writeln!(out, "pathfinder_graph_block_number {block_number}").expect("");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let metrics = Metrics::default();
+        metrics.record_request("compute_transfer", Duration::from_millis(20), true);
+        metrics.record_request("compute_transfer", Duration::from_millis(200), false);
+        metrics.record_request("no_such_method", Duration::from_millis(1), false);
+        metrics.record_flow(&FlowStats {
+            augmenting_paths: 3,
+            max_flow: U256::from(10),
+            transfer_reduction_loss: U256::from(2),
+        });
+        metrics.connection_queued();
+        let out = metrics.render(&GraphInfo {
+            edge_count: 7,
+            cache_entries: 0,
+            version: 2,
+            block_number: Some(100),
+        });
+        assert!(out.contains("pathfinder_requests_total{method=\"compute_transfer\"} 2\n"));
+        assert!(out.contains("pathfinder_request_errors_total{method=\"compute_transfer\"} 1\n"));
+        assert!(out.contains(
+            "pathfinder_request_duration_seconds_bucket{method=\"compute_transfer\",le=\"0.05\"} 1\n"
+        ));
+        assert!(out.contains("pathfinder_requests_total{method=\"unknown\"} 1\n"));
+        assert!(out.contains("pathfinder_queue_depth 1\n"));
+        assert!(out.contains("pathfinder_augmenting_paths_total 3\n"));
+        assert!(out.contains("pathfinder_transfer_reduction_loss_total 2\n"));
+        assert!(out.contains("pathfinder_edge_count 7\n"));
+        assert!(out.contains("pathfinder_graph_block_number 100\n"));
+    }
+}
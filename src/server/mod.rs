@@ -0,0 +1,963 @@
+use crate::abi;
+use crate::graph::{self, FlowPath, PathCost};
+use crate::io::{import_from_safes_binary, read_edges_binary, read_edges_csv};
+use crate::safe_db::db::DB;
+use crate::safe_db::what_if::{self, SafeChange};
+use crate::time_circles::{self, Unit};
+use crate::types::edge::EdgeDB;
+use crate::types::{Address, AddressBook, Edge, FlowResult, U256};
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt::Display;
+use std::io::Read;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::TrySendError;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod cache;
+pub mod logging;
+pub mod metrics;
+pub mod rate_limit;
+pub mod snapshot;
+
+use cache::{CacheKey, FlowCache};
+use metrics::{GraphInfo, Metrics};
+use rate_limit::{ClientId, RateLimitConfig, RateLimiter};
+
+/// JSON-RPC error code used when a client exceeded its rate limit.
+const RATE_LIMIT_ERROR: i64 = -32005;
+
+/// Maximum number of paths that can be requested from `find_paths`.
+const MAX_PATHS: usize = 100;
+
+/// Time after which reading from an idle connection fails, so that a client that
+/// does not send its request cannot block a worker (and the shutdown) forever.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the accept loop checks for new connections and the shutdown flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Number of accepted connections that can wait for a worker thread.
+    pub queue_size: usize,
+    /// Number of worker threads.
+    pub threads: u64,
+    /// Per-client rate limiting, disabled if `None`.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Maximum number of cached flow computation results, zero disables the cache.
+    pub cache_size: usize,
+    /// File the edges are persisted to and loaded from at startup, disabled if `None`.
+    pub snapshot_path: Option<String>,
+    /// Minimum time between two snapshots.
+    pub snapshot_interval: Duration,
+    /// Names of addresses used in explanations and logs.
+    pub names: AddressBook,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            queue_size: 10,
+            threads: 4,
+            rate_limit: None,
+            cache_size: 1000,
+            snapshot_path: None,
+            snapshot_interval: Duration::from_secs(300),
+            names: AddressBook::default(),
+        }
+    }
+}
+
+struct JsonRpcRequest {
+    id: Value,
+    method: String,
+    params: Value,
+    client: ClientId,
+    /// Time the request was received, in seconds since the epoch.
+    received_at: u64,
+}
+
+/// Parameters of the `load_*` methods.
+#[derive(Deserialize)]
+struct LoadParams {
+    file: String,
+    block_number: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ComputeTransferParams {
+    from: Address,
+    to: Address,
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    value: Option<U256>,
+    max_transfers: Option<u64>,
+    #[serde(default)]
+    iterative: bool,
+    /// Include the decomposition of the flow into paths in the result.
+    #[serde(default)]
+    decompose: bool,
+    /// Unit of `value` and of the additional `flow_tc` in the result.
+    #[serde(default)]
+    unit: Unit,
+    /// Time used to convert time circles as given by the client.
+    #[serde(rename = "timestamp")]
+    requested_timestamp: Option<u64>,
+    /// The requested time or the time the request was received, set by
+    /// `parse_compute_transfer_params`.
+    #[serde(skip)]
+    timestamp: u64,
+}
+
+impl ComputeTransferParams {
+    fn value(&self) -> U256 {
+        match self.value {
+            Some(value) => time_circles::convert(value, self.unit, Unit::Crc, self.timestamp),
+            None => U256::MAX,
+        }
+    }
+
+    fn max_distances(&self) -> Vec<Option<u64>> {
+        if self.iterative {
+            vec![Some(1), Some(2), None]
+        } else {
+            vec![None]
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExplainFlowParams {
+    from: Address,
+    to: Address,
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    value: Option<U256>,
+    max_distance: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct FindPathsParams {
+    from: Address,
+    to: Address,
+    #[serde(default = "default_path_count")]
+    k: usize,
+    #[serde(default)]
+    cost: PathCost,
+}
+
+fn default_path_count() -> usize {
+    5
+}
+
+/// Parameters of `what_if`: a flow computation on the loaded safes with
+/// hypothetical changes applied.
+#[derive(Deserialize)]
+struct WhatIfParams {
+    from: Address,
+    to: Address,
+    changes: Vec<SafeChange>,
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    value: Option<U256>,
+    max_distance: Option<u64>,
+    max_transfers: Option<u64>,
+}
+
+/// Flow and transfers in the format `compute_transfer` has always used: amounts as
+/// `0x`-prefixed hex strings and the amount of a transfer named `value`. This differs from
+/// the serde representation of `FlowResult` (decimal amounts, `capacity`), which is used
+/// everywhere else, but existing clients rely on it.
+#[derive(Serialize)]
+struct FlowResponse {
+    flow: String,
+    transfers: Vec<TransferResponse>,
+}
+
+#[derive(Serialize)]
+struct TransferResponse {
+    from: Address,
+    to: Address,
+    token_owner: Address,
+    value: String,
+}
+
+impl From<FlowResult> for FlowResponse {
+    fn from(result: FlowResult) -> Self {
+        FlowResponse {
+            flow: result.flow.to_string(),
+            transfers: result
+                .transfers
+                .into_iter()
+                .map(|e| TransferResponse {
+                    from: e.from,
+                    to: e.to,
+                    token_owner: e.token,
+                    value: e.capacity.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A path of the flow decomposition in a `compute_transfer` result, with the amount
+/// encoded like the other amounts of the result.
+#[derive(Serialize)]
+struct PathResponse {
+    amount: String,
+    addresses: Vec<Address>,
+    tokens: Vec<Address>,
+}
+
+impl From<FlowPath> for PathResponse {
+    fn from(path: FlowPath) -> Self {
+        PathResponse {
+            amount: path.amount.to_string(),
+            addresses: path.addresses,
+            tokens: path.tokens,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WhatIfResult {
+    #[serde(flatten)]
+    result: FlowResponse,
+    /// The flow in CRC.
+    flow_crc: String,
+}
+
+/// One (partial) result of `compute_transfer`.
+#[derive(Serialize)]
+struct ComputeTransferResult {
+    #[serde(flatten)]
+    result: FlowResponse,
+    /// The flow in CRC.
+    flow_crc: String,
+    /// The flow in time circles, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flow_tc: Option<String>,
+    /// The flow decomposed into paths, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    paths: Option<Vec<PathResponse>>,
+    /// False for the intermediate results of an iterative computation.
+    #[serde(rename = "final")]
+    is_final: bool,
+    calldata: String,
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+    api_token: Option<String>,
+    /// Value of the `X-Request-Id` header, used to correlate log records.
+    request_id: Option<String>,
+}
+
+/// The currently loaded graph.
+#[derive(Clone, Default)]
+struct Graph {
+    edges: Arc<EdgeDB>,
+    /// The safes the edges were computed from, if they were loaded from safes.
+    safes: Option<Arc<DB>>,
+    /// Incremented on every change of the edges, zero if nothing has been loaded yet.
+    version: u64,
+    /// Block number of the last load, if supplied by the client.
+    block_number: Option<u64>,
+}
+
+struct ServerState {
+    graph: RwLock<Graph>,
+    rate_limiter: Option<RateLimiter>,
+    metrics: Metrics,
+    cache: FlowCache,
+    names: AddressBook,
+}
+
+impl ServerState {
+    fn graph(&self) -> Graph {
+        self.graph.read().unwrap().clone()
+    }
+}
+
+/// Runs the server until `shutdown` is set.
+///
+/// After `shutdown` is set, no new connections are accepted, but all connections
+/// that were already accepted are still processed. If a snapshot path is configured,
+/// the graph is loaded from the snapshot on startup and written back periodically
+/// and before returning.
+pub fn start_server(listen_at: &str, config: ServerConfig, shutdown: Arc<AtomicBool>) {
+    let state = Arc::new(ServerState {
+        graph: RwLock::new(Graph::default()),
+        rate_limiter: config.rate_limit.map(RateLimiter::new),
+        metrics: Metrics::default(),
+        cache: FlowCache::new(config.cache_size),
+        names: config.names,
+    });
+    if let Some(path) = &config.snapshot_path {
+        match snapshot::read_snapshot(path) {
+            Ok(Some((edges, safes))) => {
+                let len = replace_graph(&state, edges, safes, None);
+                log::info!("Loaded {len} edges from snapshot {path}");
+            }
+            Ok(None) => log::info!("No snapshot found at {path}"),
+            Err(e) => log::error!("Error loading snapshot {path}: {e}"),
+        }
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(config.queue_size);
+    let protected_receiver = Arc::new(Mutex::new(receiver));
+    let mut workers = Vec::new();
+    for _ in 0..config.threads {
+        let rec = protected_receiver.clone();
+        let s = state.clone();
+        workers.push(thread::spawn(move || loop {
+            // Only fails once the sender is dropped and the queue is drained.
+            let Ok(socket) = rec.lock().unwrap().recv() else {
+                break;
+            };
+            s.metrics.connection_dequeued();
+            // A panic in a handler must not take the worker thread down with it.
+            match panic::catch_unwind(AssertUnwindSafe(|| handle_connection(&s, socket))) {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => log::error!("Error handling connection: {e}"),
+                Err(_) => log::error!("Panic while handling connection."),
+            }
+        }));
+    }
+    let persistence = config.snapshot_path.clone().map(|path| {
+        let s = state.clone();
+        let shutdown = shutdown.clone();
+        let interval = config.snapshot_interval;
+        thread::spawn(move || persist_periodically(&s, &path, interval, &shutdown))
+    });
+
+    let listener = TcpListener::bind(listen_at).expect("Could not create server.");
+    listener
+        .set_nonblocking(true)
+        .expect("Could not configure server socket.");
+    while !shutdown.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((socket, _)) => {
+                let configured = socket
+                    .set_nonblocking(false)
+                    .and_then(|()| socket.set_read_timeout(Some(READ_TIMEOUT)));
+                if let Err(e) = configured {
+                    log::error!("Error configuring connection: {e}");
+                    continue;
+                }
+                // Count before sending so that the worker never decrements first.
+                state.metrics.connection_queued();
+                match sender.try_send(socket) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(mut socket)) => {
+                        state.metrics.connection_dequeued();
+                        state.metrics.connection_rejected();
+                        log::warn!("Request queue full, rejecting connection.");
+                        let _ = socket.write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n");
+                    }
+                    Err(TrySendError::Disconnected(_)) => {
+                        panic!("Internal communication channel disconnected.");
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(e) => log::error!("Error accepting connection: {e}"),
+        }
+    }
+
+    log::info!("Shutting down, waiting for in-flight requests.");
+    drop(listener);
+    drop(sender);
+    for worker in workers {
+        if worker.join().is_err() {
+            log::error!("Worker thread panicked.");
+        }
+    }
+    if let Some(persistence) = persistence {
+        // Requests processed during the shutdown could have changed the graph.
+        let path = config.snapshot_path.as_ref().unwrap();
+        match persistence.join() {
+            Ok(persisted_version) => {
+                persist(&state, path, persisted_version);
+            }
+            Err(_) => log::error!("Snapshot thread panicked."),
+        }
+    }
+    log::info!("Shutdown complete.");
+}
+
+/// Writes a snapshot every `interval` if the graph changed, until `shutdown` is set.
+/// Returns the version of the graph that was last persisted.
+fn persist_periodically(
+    state: &ServerState,
+    path: &str,
+    interval: Duration,
+    shutdown: &AtomicBool,
+) -> u64 {
+    // The snapshot we loaded at startup does not need to be written back.
+    let mut persisted_version = state.graph().version;
+    let mut last_write = Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        if last_write.elapsed() >= interval {
+            persisted_version = persist(state, path, persisted_version);
+            last_write = Instant::now();
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    persisted_version
+}
+
+/// Writes a snapshot if the graph version differs from `persisted_version`.
+/// Returns the version of the graph that was last persisted.
+fn persist(state: &ServerState, path: &str, persisted_version: u64) -> u64 {
+    let graph = state.graph();
+    if graph.version == persisted_version {
+        return persisted_version;
+    }
+    match snapshot::write_snapshot(&graph.edges, graph.safes.as_deref(), path) {
+        Ok(()) => {
+            log::info!(
+                "Wrote snapshot of {} edges to {path}",
+                graph.edges.edge_count()
+            );
+            graph.version
+        }
+        Err(e) => {
+            log::error!("Error writing snapshot {path}: {e}");
+            persisted_version
+        }
+    }
+}
+
+fn handle_connection(state: &ServerState, mut socket: TcpStream) -> Result<(), Box<dyn Error>> {
+    let http_request = read_http_request(&mut socket)?;
+    let _request_id = logging::enter_request(http_request.request_id.clone());
+    match (http_request.method.as_str(), http_request.path.as_str()) {
+        ("GET", "/metrics") => {
+            let graph = state.graph();
+            let metrics = state.metrics.render(&GraphInfo {
+                edge_count: graph.edges.edge_count(),
+                cache_entries: state.cache.len(),
+                version: graph.version,
+                block_number: graph.block_number,
+            });
+            socket.write_all(
+                text_response("200 OK", "text/plain; version=0.0.4", &metrics).as_bytes(),
+            )?;
+        }
+        ("GET", "/health") => {
+            socket.write_all(text_response("200 OK", "text/plain", "OK").as_bytes())?;
+        }
+        ("GET", "/ready") => {
+            let response = if state.graph().version > 0 {
+                text_response("200 OK", "text/plain", "OK")
+            } else {
+                text_response("503 Service Unavailable", "text/plain", "No graph loaded")
+            };
+            socket.write_all(response.as_bytes())?;
+        }
+        _ => {
+            let peer = socket.peer_addr()?.ip();
+            let client = match &state.rate_limiter {
+                Some(limiter) => limiter.client_id(http_request.api_token.as_deref(), peer),
+                None => ClientId::Ip(peer),
+            };
+            let request = parse_jsonrpc_request(http_request, client)?;
+            let method = request.method.clone();
+            let start = Instant::now();
+            log::info!("Processing {method} for {}", request.client);
+            let result = handle_jsonrpc_request(state, request, &mut socket);
+            let success = matches!(result, Ok(true));
+            log::info!("Finished {method} in {} ms", start.elapsed().as_millis());
+            state
+                .metrics
+                .record_request(&method, start.elapsed(), success);
+            result?;
+        }
+    }
+    Ok(())
+}
+
+/// Processes a JSON-RPC request and writes the response.
+/// Returns false if an error response was sent.
+fn handle_jsonrpc_request(
+    state: &ServerState,
+    request: JsonRpcRequest,
+    socket: &mut TcpStream,
+) -> Result<bool, Box<dyn Error>> {
+    if let Some(limiter) = &state.rate_limiter {
+        let cost = request_cost(limiter, &request);
+        if let Err(retry_after) = limiter.acquire(&request.client, cost) {
+            log::warn!("Rate limit exceeded by {}", request.client);
+            state.metrics.request_rate_limited();
+            socket.write_all(rate_limit_error_response(request.id, retry_after).as_bytes())?;
+            return Ok(false);
+        }
+    }
+    let start = Instant::now();
+    let (response, success) = match request.method.as_str() {
+        "load_edges_binary" | "load_edges_csv" | "load_safes_binary" => {
+            match parse_params::<LoadParams>(&request.params) {
+                Ok(params) => {
+                    let loaded = match request.method.as_str() {
+                        "load_edges_binary" => read_edges_binary(&params.file).map(|e| (e, None)),
+                        "load_edges_csv" => read_edges_csv(&params.file).map(|e| (e, None)),
+                        _ => import_from_safes_binary(&params.file)
+                            .map(|db| (db.edges().clone(), Some(db))),
+                    };
+                    match loaded {
+                        Ok((edges, safes)) => (
+                            jsonrpc_response(
+                                request.id,
+                                replace_graph(state, edges, safes, params.block_number),
+                            ),
+                            true,
+                        ),
+                        Err(e) => (
+                            jsonrpc_error_response(
+                                request.id,
+                                -32000,
+                                &format!("Error loading edges: {e}"),
+                            ),
+                            false,
+                        ),
+                    }
+                }
+                Err(e) => (invalid_params_response(request.id, e), false),
+            }
+        }
+        "compute_transfer" => match parse_compute_transfer_params(&request) {
+            Ok(params) => {
+                let graph = state.graph();
+                let start = Instant::now();
+                let result = compute_transfer(&request.id, &params, &graph, state, socket);
+                if let Some(limiter) = &state.rate_limiter {
+                    let cost = limiter.cost_model().compute_time_cost(start.elapsed());
+                    limiter.charge(&request.client, cost);
+                }
+                result?;
+                return Ok(true);
+            }
+            Err(e) => (invalid_params_response(request.id, e), false),
+        },
+        "explain_flow" => match parse_params::<ExplainFlowParams>(&request.params) {
+            Ok(params) => {
+                let explanation = graph::explain_flow(
+                    &params.from,
+                    &params.to,
+                    state.graph().edges.as_ref(),
+                    params.value.unwrap_or(U256::MAX),
+                    params.max_distance,
+                    &state.names,
+                );
+                (jsonrpc_response(request.id, explanation), true)
+            }
+            Err(e) => (invalid_params_response(request.id, e), false),
+        },
+        "find_paths" => match parse_params::<FindPathsParams>(&request.params) {
+            Ok(params) if params.k > MAX_PATHS => (
+                invalid_params_response(request.id, format!("k must be at most {MAX_PATHS}")),
+                false,
+            ),
+            Ok(params) => {
+                let paths = graph::k_shortest_paths(
+                    &params.from,
+                    &params.to,
+                    state.graph().edges.as_ref(),
+                    params.k,
+                    params.cost,
+                );
+                (jsonrpc_response(request.id, paths), true)
+            }
+            Err(e) => (invalid_params_response(request.id, e), false),
+        },
+        "what_if" => match parse_params::<WhatIfParams>(&request.params) {
+            Ok(params) => match state.graph().safes {
+                Some(safes) => match what_if::apply_changes(&safes, &params.changes) {
+                    Ok(edges) => {
+                        let (flow, transfers) = graph::compute_flow(
+                            &params.from,
+                            &params.to,
+                            &edges,
+                            params.value.unwrap_or(U256::MAX),
+                            params.max_distance,
+                            params.max_transfers,
+                        );
+                        let result = WhatIfResult {
+                            flow_crc: flow.to_crc(),
+                            result: FlowResult { flow, transfers }.into(),
+                        };
+                        (jsonrpc_response(request.id, result), true)
+                    }
+                    Err(e) => (invalid_params_response(request.id, e), false),
+                },
+                None => (
+                    jsonrpc_error_response(
+                        request.id,
+                        -32000,
+                        "what_if requires safes, load them with load_safes_binary",
+                    ),
+                    false,
+                ),
+            },
+            Err(e) => (invalid_params_response(request.id, e), false),
+        },
+        "update_edges" => match parse_params::<Vec<Edge>>(&request.params) {
+            Ok(updates) => (
+                jsonrpc_response(request.id, update_edges(state, updates)),
+                true,
+            ),
+            Err(e) => (invalid_params_response(request.id, e), false),
+        },
+        _ => (
+            jsonrpc_error_response(request.id, -32601, "Method not found"),
+            false,
+        ),
+    };
+    if let Some(limiter) = &state.rate_limiter {
+        if matches!(
+            request.method.as_str(),
+            "explain_flow" | "find_paths" | "what_if"
+        ) {
+            let cost = limiter.cost_model().compute_time_cost(start.elapsed());
+            limiter.charge(&request.client, cost);
+        }
+    }
+    socket.write_all(response.as_bytes())?;
+    Ok(success)
+}
+
+/// Replaces the current graph and returns the new number of edges.
+fn replace_graph(
+    state: &ServerState,
+    edges: EdgeDB,
+    safes: Option<DB>,
+    block_number: Option<u64>,
+) -> usize {
+    let len = edges.edge_count();
+    let mut graph = state.graph.write().unwrap();
+    graph.edges = Arc::new(edges);
+    graph.safes = safes.map(Arc::new);
+    graph.version += 1;
+    graph.block_number = block_number;
+    state.cache.clear();
+    len
+}
+
+/// Estimated cost of a request before it is processed.
+fn request_cost(limiter: &RateLimiter, request: &JsonRpcRequest) -> f64 {
+    let cost_model = limiter.cost_model();
+    let mut cost = cost_model.base;
+    if request.method == "compute_transfer" {
+        if let Ok(params) = parse_compute_transfer_params(request) {
+            for max_distance in params.max_distances() {
+                cost += cost_model.flow_cost(max_distance, params.value());
+            }
+        }
+    } else if request.method == "explain_flow" {
+        if let Ok(params) = parse_params::<ExplainFlowParams>(&request.params) {
+            cost += cost_model.flow_cost(params.max_distance, params.value.unwrap_or(U256::MAX));
+        }
+    } else if request.method == "what_if" {
+        if let Ok(params) = parse_params::<WhatIfParams>(&request.params) {
+            cost += cost_model.flow_cost(params.max_distance, params.value.unwrap_or(U256::MAX));
+        }
+    } else if request.method == "find_paths" {
+        if let Ok(params) = parse_params::<FindPathsParams>(&request.params) {
+            cost += cost_model.paths_cost(params.k.min(MAX_PATHS));
+        }
+    }
+    cost
+}
+
+/// Deserializes an amount given in raw units or in CRC, see `U256::parse_amount`.
+fn deserialize_amount<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<U256>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(amount) => U256::parse_amount(&amount)
+            .map(Some)
+            .map_err(de::Error::custom),
+        value => Option::<U256>::deserialize(value).map_err(de::Error::custom),
+    }
+}
+
+/// Parses the parameters of `compute_transfer` and resolves the timestamp once, so that
+/// the cost, the cache key and the computation use the same conversion of time circles.
+/// Without a timestamp, the time the request was received is used, rounded down to the
+/// minute so that results can be cached.
+fn parse_compute_transfer_params(
+    request: &JsonRpcRequest,
+) -> Result<ComputeTransferParams, String> {
+    let mut params =
+        parse_params::<ComputeTransferParams>(&request.params).map_err(|e| e.to_string())?;
+    params.timestamp = match params.requested_timestamp {
+        Some(timestamp) => time_circles::validate_timestamp(timestamp, request.received_at)
+            .map_err(|e| e.to_string())?,
+        None => request.received_at - request.received_at % 60,
+    };
+    Ok(params)
+}
+
+fn parse_params<T: DeserializeOwned>(params: &Value) -> Result<T, serde_json::Error> {
+    T::deserialize(params)
+}
+
+fn compute_transfer(
+    id: &Value,
+    params: &ComputeTransferParams,
+    graph: &Graph,
+    state: &ServerState,
+    socket: &mut TcpStream,
+) -> Result<(), Box<dyn Error>> {
+    socket.write_all(chunked_header().as_bytes())?;
+    let timestamp = params.timestamp;
+    for max_distance in params.max_distances() {
+        let key = CacheKey {
+            from: params.from,
+            to: params.to,
+            value: params.value(),
+            max_distance,
+            max_transfers: params.max_transfers,
+        };
+        let (flow, transfers) = match state.cache.get(&key, graph.version) {
+            Some(result) => {
+                state.metrics.cache_hit();
+                log::info!(
+                    "Using cached flow from {} to {} with max distance {max_distance:?}",
+                    state.names.describe(&key.from),
+                    state.names.describe(&key.to)
+                );
+                result
+            }
+            None => {
+                state.metrics.cache_miss();
+                let (flow, transfers, stats) = graph::compute_flow_with_stats(
+                    &key.from,
+                    &key.to,
+                    graph.edges.as_ref(),
+                    key.value,
+                    max_distance,
+                    params.max_transfers,
+                );
+                log::info!(
+                    "Computed flow from {} to {} with max distance {max_distance:?}: {flow}",
+                    state.names.describe(&key.from),
+                    state.names.describe(&key.to)
+                );
+                state.metrics.record_flow(&stats);
+                state
+                    .cache
+                    .insert(key, graph.version, flow, transfers.clone());
+                (flow, transfers)
+            }
+        };
+        let calldata = abi::to_hex(&abi::encode_transfer_through(&transfers));
+        let flow_tc =
+            (params.unit == Unit::Tc).then(|| time_circles::crc_to_tc(flow, timestamp).to_crc());
+        let paths = params.decompose.then(|| {
+            graph::decompose_flow(&params.from, &params.to, &transfers)
+                .into_iter()
+                .map(|mut path| {
+                    path.amount =
+                        time_circles::convert(path.amount, Unit::Crc, params.unit, timestamp);
+                    path.into()
+                })
+                .collect()
+        });
+        let result = ComputeTransferResult {
+            flow_crc: flow.to_crc(),
+            flow_tc,
+            paths,
+            result: FlowResult { flow, transfers }
+                .to_unit(params.unit, timestamp)
+                .into(),
+            is_final: max_distance.is_none(),
+            calldata,
+        };
+        socket.write_all(
+            chunked_response(&(jsonrpc_result(id.clone(), result) + "\r\n")).as_bytes(),
+        )?;
+    }
+    socket.write_all(chunked_close().as_bytes())?;
+    Ok(())
+}
+
+/// Applies the edge updates and returns the new number of edges.
+fn update_edges(state: &ServerState, updates: Vec<Edge>) -> usize {
+    if updates.is_empty() {
+        return state.graph().edges.edge_count();
+    }
+
+    // Hold the write lock while applying the updates, so that concurrent updates or loads
+    // are not lost and the cache is only carried over from the graph that was updated.
+    let mut graph = state.graph.write().unwrap();
+    let mut updating_edges = graph.edges.as_ref().clone();
+    for update in &updates {
+        updating_edges.update(*update);
+    }
+    let len = updating_edges.edge_count();
+    graph.edges = Arc::new(updating_edges);
+    // The safes do not reflect the updated edges anymore.
+    graph.safes = None;
+    graph.version += 1;
+    state
+        .cache
+        .edges_updated(graph.version - 1, graph.version, &updates);
+    len
+}
+
+fn parse_jsonrpc_request(
+    http_request: HttpRequest,
+    client: ClientId,
+) -> Result<JsonRpcRequest, Box<dyn Error>> {
+    let mut request: Value = serde_json::from_slice(&http_request.body)?;
+    log::debug!("Request: {request}");
+    let id = request.get_mut("id").map(Value::take).unwrap_or_default();
+    let params = request
+        .get_mut("params")
+        .map(Value::take)
+        .unwrap_or_default();
+    match request["method"].as_str() {
+        Some(method) => Ok(JsonRpcRequest {
+            id,
+            method: method.to_string(),
+            params,
+            client,
+            received_at: time_circles::now(),
+        }),
+        _ => Err(From::from(format!("Invalid JSON-RPC request: {request}"))),
+    }
+}
+
+fn read_http_request(socket: &mut TcpStream) -> Result<HttpRequest, Box<dyn Error>> {
+    let mut reader = BufReader::new(socket);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let mut length = 0;
+    let mut api_token = None;
+    let mut request_id = None;
+    for result in reader.by_ref().lines() {
+        let l = result?;
+        if l.is_empty() {
+            break;
+        }
+
+        let lowercase = l.to_lowercase();
+        if let Some(value) = lowercase.strip_prefix("content-length:") {
+            length = value.trim().parse::<usize>()?;
+        } else if lowercase.starts_with("x-api-key:") {
+            api_token = Some(l["x-api-key:".len()..].trim().to_string());
+        } else if lowercase.starts_with("authorization: bearer ") {
+            api_token = Some(l["authorization: bearer ".len()..].trim().to_string());
+        } else if lowercase.starts_with("x-request-id:") {
+            request_id = Some(l["x-request-id:".len()..].trim().to_string());
+        }
+    }
+    let mut payload = vec![0u8; length];
+
+    reader.read_exact(payload.as_mut_slice())?;
+    Ok(HttpRequest {
+        method,
+        path,
+        body: payload,
+        api_token,
+        request_id,
+    })
+}
+
+fn text_response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn jsonrpc_response(id: Value, result: impl Serialize) -> String {
+    let payload = jsonrpc_result(id, result);
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+        payload.len(),
+        payload
+    )
+}
+
+fn jsonrpc_result(id: Value, result: impl Serialize) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": result,
+    })
+    .to_string()
+}
+
+fn rate_limit_error_response(id: Value, retry_after: Duration) -> String {
+    let retry_after = retry_after.as_secs_f64().ceil().min(u32::MAX as f64) as u64;
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": RATE_LIMIT_ERROR,
+            "message": "Rate limit exceeded",
+            "data": {
+                "retry_after": retry_after
+            }
+        }
+    })
+    .to_string();
+    format!(
+        "HTTP/1.1 200 OK\r\nRetry-After: {}\r\nContent-Length: {}\r\n\r\n{}",
+        retry_after,
+        payload.len(),
+        payload
+    )
+}
+
+fn invalid_params_response(id: Value, error: impl Display) -> String {
+    jsonrpc_error_response(id, -32602, &format!("Invalid arguments: {error}"))
+}
+
+fn jsonrpc_error_response(id: Value, code: i64, message: &str) -> String {
+    let payload = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message
+        }
+    })
+    .to_string();
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+        payload.len(),
+        payload
+    )
+}
+
+fn chunked_header() -> String {
+    "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_string()
+}
+
+fn chunked_response(data: &str) -> String {
+    if data.is_empty() {
+        String::new()
+    } else {
+        format!("{:x}\r\n{}\r\n", data.len(), data)
+    }
+}
+
+fn chunked_close() -> String {
+    "0\r\n\r\n".to_string()
+}
@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::U256;
+
+/// Once there are more buckets than this, full buckets are dropped
+/// (a full bucket is indistinguishable from a client we have never seen).
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ClientId {
+    /// Identified by a configured API key (`X-Api-Key` or `Authorization: Bearer` header).
+    Token(String),
+    /// Identified by the peer IP address.
+    Ip(IpAddr),
+}
+
+impl Display for ClientId {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ClientId::Token(_) => write!(f, "token"),
+            ClientId::Ip(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+/// Weights used to estimate the cost of a request.
+/// Costs are abstract units that are drawn from the client's budget.
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    /// Cost of every request, regardless of the method.
+    pub base: f64,
+    /// Cost per hop of `max_distance` for a flow computation.
+    pub per_hop: f64,
+    /// Cost of a flow computation without `max_distance`.
+    pub unbounded_distance: f64,
+    /// Cost per decimal digit of the requested value above one CRC (10^18).
+    pub per_value_digit: f64,
+    /// Cost of a flow computation without a requested value.
+    pub unbounded_value: f64,
+    /// Cost per requested path of `find_paths`.
+    pub per_path: f64,
+    /// Cost per millisecond of measured computation time.
+    pub per_compute_ms: f64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            base: 1.0,
+            per_hop: 1.0,
+            unbounded_distance: 10.0,
+            per_value_digit: 0.5,
+            unbounded_value: 5.0,
+            per_path: 0.5,
+            per_compute_ms: 0.01,
+        }
+    }
+}
+
+/// Parses comma-separated `name=value` pairs, weights that are not given keep
+/// their default value, e.g. `per_hop=2,per_compute_ms=0.1`.
+impl FromStr for CostModel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model = CostModel::default();
+        for pair in s.split(',').filter(|p| !p.is_empty()) {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=value, got \"{pair}\""))?;
+            let value: f64 = value
+                .parse()
+                .map_err(|_| format!("invalid weight \"{value}\" for {name}"))?;
+            let weight = match name {
+                "base" => &mut model.base,
+                "per_hop" => &mut model.per_hop,
+                "unbounded_distance" => &mut model.unbounded_distance,
+                "per_value_digit" => &mut model.per_value_digit,
+                "unbounded_value" => &mut model.unbounded_value,
+                "per_path" => &mut model.per_path,
+                "per_compute_ms" => &mut model.per_compute_ms,
+                _ => return Err(format!("unknown weight {name}")),
+            };
+            *weight = value;
+        }
+        Ok(model)
+    }
+}
+
+impl CostModel {
+    /// Estimated cost of a single flow computation, charged before it is started.
+    pub fn flow_cost(&self, max_distance: Option<u64>, value: U256) -> f64 {
+        let distance_cost = match max_distance {
+            Some(d) => self.per_hop * d as f64,
+            None => self.unbounded_distance,
+        };
+        let value_cost = if value == U256::MAX {
+            self.unbounded_value
+        } else {
+            let digits = value.to_decimal().len();
+            self.per_value_digit * digits.saturating_sub(19) as f64
+        };
+        distance_cost + value_cost
+    }
+
+    /// Estimated cost of finding `k` shortest paths, charged before it is started.
+    pub fn paths_cost(&self, k: usize) -> f64 {
+        self.per_path * k as f64
+    }
+
+    /// Cost of computation time that was actually spent, charged afterwards.
+    pub fn compute_time_cost(&self, elapsed: Duration) -> f64 {
+        self.per_compute_ms * elapsed.as_secs_f64() * 1000.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Maximum budget a client can accumulate.
+    pub budget: f64,
+    /// Budget refilled per second.
+    pub refill_per_second: f64,
+    pub cost: CostModel,
+    /// API keys that identify a client. Requests with other keys are limited by IP address,
+    /// so that a client cannot get a fresh budget by sending a new key.
+    pub api_keys: HashSet<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            budget: 100.0,
+            refill_per_second: 2.0,
+            cost: CostModel::default(),
+            api_keys: HashSet::new(),
+        }
+    }
+}
+
+/// Reads API keys, one per line. Empty lines are ignored.
+pub fn read_api_keys(path: &str) -> Result<HashSet<String>, io::Error> {
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+struct Bucket {
+    available: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket rate limiter, one bucket per client.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<ClientId, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> RateLimiter {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn cost_model(&self) -> &CostModel {
+        &self.config.cost
+    }
+
+    /// Identifies a client by its API key if the key is configured, by `peer` otherwise.
+    pub fn client_id(&self, api_key: Option<&str>, peer: IpAddr) -> ClientId {
+        match api_key {
+            Some(key) if self.config.api_keys.contains(key) => ClientId::Token(key.to_string()),
+            _ => ClientId::Ip(peer),
+        }
+    }
+
+    /// Takes `cost` from the budget of `client` if enough is available.
+    /// Otherwise returns the time after which the request can be retried.
+    pub fn acquire(&self, client: &ClientId, cost: f64) -> Result<(), Duration> {
+        self.acquire_at(client, cost, Instant::now())
+    }
+
+    /// Takes `cost` from the budget of `client` unconditionally,
+    /// potentially leaving the client in debt.
+    pub fn charge(&self, client: &ClientId, cost: f64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = self.refilled_bucket(&mut buckets, client, Instant::now());
+        bucket.available -= cost;
+    }
+
+    fn acquire_at(&self, client: &ClientId, cost: f64, now: Instant) -> Result<(), Duration> {
+        // A request that costs more than the full budget would never succeed.
+        let cost = cost.min(self.config.budget);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_TRACKED_CLIENTS {
+            self.drop_full_buckets(&mut buckets, now);
+        }
+        let refill_per_second = self.config.refill_per_second;
+        let bucket = self.refilled_bucket(&mut buckets, client, now);
+        if bucket.available >= cost {
+            bucket.available -= cost;
+            Ok(())
+        } else if refill_per_second <= 0.0 {
+            Err(Duration::MAX)
+        } else {
+            Err(
+                Duration::try_from_secs_f64((cost - bucket.available) / refill_per_second)
+                    .unwrap_or(Duration::MAX),
+            )
+        }
+    }
+
+    fn refilled_bucket<'a>(
+        &self,
+        buckets: &'a mut HashMap<ClientId, Bucket>,
+        client: &ClientId,
+        now: Instant,
+    ) -> &'a mut Bucket {
+        let bucket = buckets.entry(client.clone()).or_insert(Bucket {
+            available: self.config.budget,
+            last_refill: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.available = (bucket.available
+            + elapsed.as_secs_f64() * self.config.refill_per_second)
+            .min(self.config.budget);
+        bucket.last_refill = now;
+        bucket
+    }
+
+    fn drop_full_buckets(&self, buckets: &mut HashMap<ClientId, Bucket>, now: Instant) {
+        let budget = self.config.budget;
+        let refill_per_second = self.config.refill_per_second;
+        buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill);
+            bucket.available + elapsed.as_secs_f64() * refill_per_second < budget
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limiter(budget: f64, refill_per_second: f64) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            budget,
+            refill_per_second,
+            cost: CostModel::default(),
+            api_keys: HashSet::from(["abc".to_string()]),
+        })
+    }
+
+    #[test]
+    fn exhaust_and_refill() {
+        let limiter = limiter(10.0, 1.0);
+        let client = ClientId::Token("abc".to_string());
+        let start = Instant::now();
+        assert!(limiter.acquire_at(&client, 6.0, start).is_ok());
+        let retry_after = limiter.acquire_at(&client, 6.0, start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(2));
+        assert!(limiter
+            .acquire_at(&client, 6.0, start + Duration::from_secs(2))
+            .is_ok());
+    }
+
+    #[test]
+    fn clients_are_independent() {
+        let limiter = limiter(5.0, 1.0);
+        let a = ClientId::Ip("127.0.0.1".parse().unwrap());
+        let b = ClientId::Ip("127.0.0.2".parse().unwrap());
+        let now = Instant::now();
+        assert!(limiter.acquire_at(&a, 5.0, now).is_ok());
+        assert!(limiter.acquire_at(&a, 1.0, now).is_err());
+        assert!(limiter.acquire_at(&b, 5.0, now).is_ok());
+    }
+
+    #[test]
+    fn cost_is_capped_at_budget() {
+        let limiter = limiter(5.0, 1.0);
+        let client = ClientId::Token("abc".to_string());
+        assert!(limiter.acquire_at(&client, 50.0, Instant::now()).is_ok());
+    }
+
+    #[test]
+    fn tiny_refill() {
+        let limiter = limiter(5.0, 1e-300);
+        let client = ClientId::Token("abc".to_string());
+        let now = Instant::now();
+        assert!(limiter.acquire_at(&client, 5.0, now).is_ok());
+        assert_eq!(limiter.acquire_at(&client, 5.0, now), Err(Duration::MAX));
+    }
+
+    #[test]
+    fn unknown_api_keys() {
+        let limiter = limiter(5.0, 1.0);
+        let peer = "127.0.0.1".parse().unwrap();
+        assert_eq!(
+            limiter.client_id(Some("abc"), peer),
+            ClientId::Token("abc".to_string())
+        );
+        assert_eq!(limiter.client_id(Some("random"), peer), ClientId::Ip(peer));
+        assert_eq!(limiter.client_id(None, peer), ClientId::Ip(peer));
+    }
+
+    #[test]
+    fn parse_cost_model() {
+        let cost: CostModel = "per_hop=2,per_compute_ms=0.5".parse().unwrap();
+        assert_eq!(cost.per_hop, 2.0);
+        assert_eq!(cost.per_compute_ms, 0.5);
+        assert_eq!(cost.base, CostModel::default().base);
+        assert!("per_hop".parse::<CostModel>().is_err());
+        assert!("nope=1".parse::<CostModel>().is_err());
+    }
+
+    #[test]
+    fn flow_cost() {
+        let cost = CostModel::default();
+        let one_crc = U256::from(1_000_000_000_000_000_000);
+        assert_eq!(cost.flow_cost(Some(3), one_crc), 3.0);
+        assert_eq!(
+            cost.flow_cost(Some(3), one_crc * U256::from(100)),
+            3.0 + 2.0 * 0.5
+        );
+        assert_eq!(cost.flow_cost(None, U256::MAX), 15.0);
+        assert_eq!(cost.paths_cost(100), 50.0);
+    }
+}
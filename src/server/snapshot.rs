@@ -0,0 +1,117 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::io::{
+    import_from_safes_binary, read_edges_binary, write_edges_binary, write_safes_binary,
+};
+use crate::safe_db::db::DB;
+use crate::types::edge::EdgeDB;
+
+/// Writes the edges to `path` in the binary edge format and, if the edges were computed
+/// from safes, the safes to `<path>.safes` in the binary safes format.
+/// The files are first written under a temporary name and then renamed,
+/// so that `path` always contains a complete snapshot.
+pub fn write_snapshot(edges: &EdgeDB, safes: Option<&DB>, path: &str) -> Result<(), io::Error> {
+    let safes_path = safes_path(path);
+    match safes {
+        Some(safes) => {
+            let tmp_path = format!("{safes_path}.tmp");
+            write_safes_binary(safes, &tmp_path)?;
+            fs::rename(&tmp_path, &safes_path)?;
+        }
+        None if Path::new(&safes_path).exists() => fs::remove_file(&safes_path)?,
+        None => {}
+    }
+    let tmp_path = format!("{path}.tmp");
+    write_edges_binary(edges, &tmp_path)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Reads the snapshot at `path`, together with the safes if they were written and
+/// still match the edges. Returns `None` if there is no snapshot.
+pub fn read_snapshot(path: &str) -> Result<Option<(EdgeDB, Option<DB>)>, io::Error> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let edges = read_edges_binary(&path.to_string())?;
+    let safes_path = safes_path(path);
+    if !Path::new(&safes_path).exists() {
+        return Ok(Some((edges, None)));
+    }
+    let safes = import_from_safes_binary(&safes_path)?;
+    // The edges are written last, so they could be newer if writing was interrupted.
+    if sorted_edges(safes.edges()) == sorted_edges(&edges) {
+        Ok(Some((edges, Some(safes))))
+    } else {
+        log::warn!("Ignoring {safes_path}, it does not match the edges of the snapshot");
+        Ok(Some((edges, None)))
+    }
+}
+
+fn safes_path(path: &str) -> String {
+    format!("{path}.safes")
+}
+
+fn sorted_edges(edges: &EdgeDB) -> Vec<crate::types::Edge> {
+    let mut edges = edges.edges().clone();
+    edges.sort();
+    edges
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Address, Edge, Safe, U256};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("pathfinder2_snapshot_{}.dat", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        assert!(read_snapshot(&path).unwrap().is_none());
+        let edges = EdgeDB::new(vec![Edge {
+            from: Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E"),
+            to: Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37"),
+            token: Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E"),
+            capacity: U256::from(10),
+        }]);
+        write_snapshot(&edges, None, &path).unwrap();
+        let (read, safes) = read_snapshot(&path).unwrap().unwrap();
+        assert_eq!(read.edges(), edges.edges());
+        assert!(safes.is_none());
+
+        let (a, b) = (Address::from([1; 20]), Address::from([2; 20]));
+        let safe = |address, balances: Vec<(Address, u128)>, limits: Vec<(Address, u8)>| Safe {
+            token_address: address,
+            balances: balances
+                .into_iter()
+                .map(|(t, b)| (t, U256::from(b)))
+                .collect(),
+            limit_percentage: limits.into_iter().collect(),
+            organization: false,
+        };
+        let db = DB::new(
+            BTreeMap::from([
+                (a, safe(a, vec![(a, 100)], vec![(b, 50)])),
+                (b, safe(b, vec![(b, 100)], vec![])),
+            ]),
+            BTreeMap::from([(a, a), (b, b)]),
+        );
+        write_snapshot(db.edges(), Some(&db), &path).unwrap();
+        let (read, safes) = read_snapshot(&path).unwrap().unwrap();
+        assert_eq!(sorted_edges(&read), sorted_edges(db.edges()));
+        assert_eq!(safes.unwrap().safes(), db.safes());
+
+        // Safes that do not match the edges are ignored.
+        write_snapshot(db.edges(), Some(&db), &path).unwrap();
+        write_edges_binary(&edges, &path).unwrap();
+        assert!(read_snapshot(&path).unwrap().unwrap().1.is_none());
+
+        write_snapshot(&edges, None, &path).unwrap();
+        assert!(!Path::new(&safes_path(&path)).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
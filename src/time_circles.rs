@@ -0,0 +1,223 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{FlowResult, U256};
+
+/// Start of the Circles issuance (2020-10-15T18:25:30Z).
+pub const DAY0: u64 = 1602786330;
+/// Length of an inflation period in seconds (365.25 days).
+pub const PERIOD: u64 = 31557600;
+/// Daily payout in CRC during the first period.
+const BASE_DAILY_PAYOUT: u64 = 8;
+/// The daily payout grows by 7% per period, expressed as a fraction.
+const INFLATION_NUMERATOR: u64 = 107;
+const INFLATION_DENOMINATOR: u64 = 100;
+/// One time circle corresponds to one hour of the daily payout.
+const HOURS_PER_DAY: u64 = 24;
+/// How far in the future a timestamp may be.
+const MAX_FUTURE: u64 = 24 * 60 * 60;
+
+/// Unit of a token amount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    /// Raw token amounts (CRC).
+    #[default]
+    Crc,
+    /// Time circles: amounts relative to the daily payout at a given time,
+    /// so that 24 time circles are always worth one day of issuance.
+    Tc,
+}
+
+impl FromStr for Unit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crc" => Ok(Unit::Crc),
+            "tc" => Ok(Unit::Tc),
+            _ => Err("expected crc or tc".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTimestamp(pub u64);
+
+impl Display for InvalidTimestamp {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Timestamp {} is before the start of Circles or more than a day in the future",
+            self.0
+        )
+    }
+}
+
+impl Error for InvalidTimestamp {}
+
+/// Checks that `timestamp` is between the start of Circles and one day after `now`.
+/// The cost of a conversion grows with the number of periods since the start,
+/// so timestamps from untrusted input have to be checked before converting.
+pub fn validate_timestamp(timestamp: u64, now: u64) -> Result<u64, InvalidTimestamp> {
+    if (DAY0..=now.saturating_add(MAX_FUTURE)).contains(&timestamp) {
+        Ok(timestamp)
+    } else {
+        Err(InvalidTimestamp(timestamp))
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(DAY0)
+}
+
+/// Returns the daily payout at `timestamp` as a fraction `(numerator, denominator)` of
+/// `BASE_DAILY_PAYOUT`, interpolated linearly between the values of two periods.
+/// Panics if the timestamp is so far in the future that the number of periods does not fit
+/// a `u32`, see `validate_timestamp`.
+fn payout_factor(timestamp: u64) -> (BigUint, BigUint) {
+    let elapsed = timestamp.saturating_sub(DAY0);
+    let periods = u32::try_from(elapsed / PERIOD)
+        .ok()
+        .filter(|periods| *periods < u32::MAX)
+        .expect("Timestamp too far in the future");
+    let in_period = elapsed % PERIOD;
+    let numerator = BigUint::from(INFLATION_NUMERATOR).pow(periods)
+        * (BigUint::from(PERIOD - in_period) * INFLATION_DENOMINATOR
+            + BigUint::from(in_period) * INFLATION_NUMERATOR);
+    let denominator = BigUint::from(INFLATION_DENOMINATOR).pow(periods + 1) * BigUint::from(PERIOD);
+    (numerator, denominator)
+}
+
+/// Returns the daily payout of a single account at `timestamp` in the smallest unit.
+pub fn daily_payout(timestamp: u64) -> U256 {
+    let (numerator, denominator) = payout_factor(timestamp);
+    let base = BigUint::from(BASE_DAILY_PAYOUT) * BigUint::from(10u32).pow(18);
+    U256::from_bigint_truncating(base * numerator / denominator)
+}
+
+/// Converts a raw token amount into time circles at `timestamp` (both with 18 decimals),
+/// rounding down.
+pub fn crc_to_tc(amount: U256, timestamp: u64) -> U256 {
+    let (numerator, denominator) = payout_factor(timestamp);
+    let tc = BigUint::from(amount) * HOURS_PER_DAY * denominator / (numerator * BASE_DAILY_PAYOUT);
+    U256::from_bigint_truncating(tc)
+}
+
+/// Converts an amount in time circles at `timestamp` into raw tokens (both with 18 decimals),
+/// rounding down.
+pub fn tc_to_crc(amount: U256, timestamp: u64) -> U256 {
+    let (numerator, denominator) = payout_factor(timestamp);
+    let crc = BigUint::from(amount) * BASE_DAILY_PAYOUT * numerator / (denominator * HOURS_PER_DAY);
+    U256::from_bigint_truncating(crc)
+}
+
+/// Converts `amount` from unit `from` into unit `to` at `timestamp`.
+pub fn convert(amount: U256, from: Unit, to: Unit, timestamp: u64) -> U256 {
+    match (from, to) {
+        (Unit::Crc, Unit::Tc) => crc_to_tc(amount, timestamp),
+        (Unit::Tc, Unit::Crc) => tc_to_crc(amount, timestamp),
+        _ => amount,
+    }
+}
+
+impl FlowResult {
+    /// Returns the result with the flow and all transfer amounts expressed in `unit`.
+    /// Only meant for display, the amounts of the transfers are rounded individually.
+    pub fn to_unit(&self, unit: Unit, timestamp: u64) -> FlowResult {
+        let convert = |amount| convert(amount, Unit::Crc, unit, timestamp);
+        FlowResult {
+            flow: convert(self.flow),
+            transfers: self
+                .transfers
+                .iter()
+                .map(|e| {
+                    let mut e = *e;
+                    e.capacity = convert(e.capacity);
+                    e
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Address, Edge};
+
+    const CRC: u128 = 1000000000000000000;
+
+    #[test]
+    fn payout() {
+        assert_eq!(daily_payout(DAY0), U256::from(8 * CRC));
+        assert_eq!(daily_payout(DAY0 - 1000), U256::from(8 * CRC));
+        assert_eq!(daily_payout(DAY0 + PERIOD), U256::from(856 * CRC / 100));
+        // Halfway through the second period, between 8.56 and 9.1592.
+        assert_eq!(
+            daily_payout(DAY0 + PERIOD + PERIOD / 2),
+            U256::from(88596 * CRC / 10000)
+        );
+    }
+
+    #[test]
+    fn conversion() {
+        // At the start, one day of payout (8 CRC) is 24 TC.
+        assert_eq!(crc_to_tc(U256::from(8 * CRC), DAY0), U256::from(24 * CRC));
+        assert_eq!(tc_to_crc(U256::from(24 * CRC), DAY0), U256::from(8 * CRC));
+        let t = DAY0 + 3 * PERIOD + 12345;
+        let payout = daily_payout(t);
+        let tc = crc_to_tc(payout, t);
+        assert!(tc <= U256::from(24 * CRC) && U256::from(24 * CRC) - tc <= U256::from(10));
+        let amount = U256::from(123 * CRC);
+        let roundtrip = tc_to_crc(crc_to_tc(amount, t), t);
+        assert!(roundtrip <= amount && amount - roundtrip <= U256::from(1));
+        assert_eq!(convert(amount, Unit::Crc, Unit::Crc, t), amount);
+    }
+
+    #[test]
+    fn timestamps() {
+        let now = DAY0 + 5 * PERIOD;
+        assert_eq!(validate_timestamp(DAY0, now), Ok(DAY0));
+        assert_eq!(
+            validate_timestamp(now + MAX_FUTURE, now),
+            Ok(now + MAX_FUTURE)
+        );
+        assert!(validate_timestamp(DAY0 - 1, now).is_err());
+        assert!(validate_timestamp(now + MAX_FUTURE + 1, now).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Timestamp too far in the future")]
+    fn timestamp_overflow() {
+        daily_payout(u64::MAX);
+    }
+
+    #[test]
+    fn flow_result_to_unit() {
+        let (a, b) = (Address::from([1; 20]), Address::from([2; 20]));
+        let edge = |capacity: u128| Edge {
+            from: a,
+            to: b,
+            token: a,
+            capacity: U256::from(capacity),
+        };
+        let result = FlowResult {
+            flow: U256::from(8 * CRC),
+            transfers: vec![edge(6 * CRC), edge(2 * CRC)],
+        };
+        assert_eq!(result.to_unit(Unit::Crc, DAY0), result);
+        let tc = result.to_unit(Unit::Tc, DAY0);
+        assert_eq!(tc.flow, U256::from(24 * CRC));
+        assert_eq!(tc.transfers, vec![edge(18 * CRC), edge(6 * CRC)]);
+        assert_eq!("tc".parse(), Ok(Unit::Tc));
+        assert!("TC".parse::<Unit>().is_err());
+    }
+}
@@ -1,8 +1,39 @@
+use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
+use crate::hash::keccak256;
 
 #[derive(Clone, Copy, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Address([u8; 20]);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressParseError {
+    InvalidLength(usize),
+    InvalidCharacter(char),
+    /// The address uses mixed case but does not match its EIP-55 checksum.
+    InvalidChecksum(String),
+}
+
+impl Display for AddressParseError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            AddressParseError::InvalidLength(len) => {
+                write!(f, "Expected 40 hex characters, but got {len}")
+            }
+            AddressParseError::InvalidCharacter(c) => write!(f, "Invalid hex character '{c}'"),
+            AddressParseError::InvalidChecksum(expected) => {
+                write!(f, "Invalid checksum, expected {expected}")
+            }
+        }
+    }
+}
+
+impl Error for AddressParseError {}
+
 impl Address {
     pub fn short(&self) -> String {
         format!("{self}")[..8].to_string()
@@ -12,8 +43,55 @@ impl Address {
         self.0
     }
 
+    /// Returns the EIP-55 mixed-case checksum encoding.
     pub fn to_checksummed_hex(&self) -> String {
-        eth_checksum::checksum(&self.to_string())
+        let hex = format!("{self}")[2..].to_string();
+        let hash = keccak256(hex.as_bytes());
+        let mut result = String::with_capacity(42);
+        result.push_str("0x");
+        for (i, c) in hex.chars().enumerate() {
+            let nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            result.push(if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            });
+        }
+        result
+    }
+
+    /// Parses a hex address with optional `0x` prefix, ignoring the checksum.
+    pub fn parse_lenient(input: &str) -> Result<Address, AddressParseError> {
+        let hex = input.strip_prefix("0x").unwrap_or(input);
+        if hex.len() != 20 * 2 {
+            return Err(AddressParseError::InvalidLength(hex.len()));
+        }
+        if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(AddressParseError::InvalidCharacter(c));
+        }
+        let mut data = [0u8; 20];
+        data.iter_mut().enumerate().for_each(|(i, b)| {
+            *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        });
+        Ok(Address(data))
+    }
+
+    /// Parses a hex address with optional `0x` prefix. Addresses that use both
+    /// upper and lower case letters have to match their EIP-55 checksum.
+    pub fn parse_strict(input: &str) -> Result<Address, AddressParseError> {
+        let address = Address::parse_lenient(input)?;
+        let hex = input.strip_prefix("0x").unwrap_or(input);
+        let mixed_case = hex.chars().any(|c| c.is_ascii_uppercase())
+            && hex.chars().any(|c| c.is_ascii_lowercase());
+        let checksummed = address.to_checksummed_hex();
+        if mixed_case && hex != &checksummed[2..] {
+            return Err(AddressParseError::InvalidChecksum(checksummed));
+        }
+        Ok(address)
     }
 }
 
@@ -29,15 +107,18 @@ impl From<[u8; 20]> for Address {
     }
 }
 
+/// Lenient conversion that panics on invalid input, use `str::parse` to validate
+/// the checksum and handle errors.
 impl From<&str> for Address {
     fn from(item: &str) -> Self {
-        let item = item.strip_prefix("0x").unwrap_or(item);
-        assert!(item.len() == 20 * 2);
-        let mut data = [0u8; 20];
-        data.iter_mut().enumerate().for_each(|(i, b)| {
-            *b = u8::from_str_radix(&item[2 * i..2 * i + 2], 16).unwrap();
-        });
-        Address(data)
+        Address::parse_lenient(item).unwrap_or_else(|e| panic!("Invalid address {item}: {e}"))
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Address::parse_strict(s)
     }
 }
 
@@ -50,3 +131,67 @@ impl Display for Address {
         Ok(())
     }
 }
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_checksummed_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn checksum() {
+        let address = Address::from(CHECKSUMMED.to_lowercase().as_str());
+        assert_eq!(address.to_checksummed_hex(), CHECKSUMMED);
+        assert_eq!(
+            Address::from("0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359").to_checksummed_hex(),
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let address = Address::from(CHECKSUMMED);
+        assert_eq!(CHECKSUMMED.parse(), Ok(address));
+        assert_eq!(CHECKSUMMED.to_lowercase().parse(), Ok(address));
+        assert_eq!(CHECKSUMMED[2..].to_uppercase().parse(), Ok(address));
+        let wrong_case = CHECKSUMMED.replace('a', "A");
+        assert_eq!(
+            wrong_case.parse::<Address>(),
+            Err(AddressParseError::InvalidChecksum(CHECKSUMMED.to_string()))
+        );
+        assert_eq!(Address::parse_lenient(&wrong_case), Ok(address));
+        assert_eq!(
+            "0x1234".parse::<Address>(),
+            Err(AddressParseError::InvalidLength(4))
+        );
+        assert_eq!(
+            Address::parse_lenient(&CHECKSUMMED.replace('a', "g")),
+            Err(AddressParseError::InvalidCharacter('g'))
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let address = Address::from(CHECKSUMMED);
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{CHECKSUMMED}\""));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+        assert!(
+            serde_json::from_str::<Address>("\"0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed\"")
+                .is_err()
+        );
+    }
+}
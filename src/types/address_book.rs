@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::types::address::AddressParseError;
+use crate::types::Address;
+
+/// Human-readable names of addresses, e.g. Circles usernames.
+///
+/// Names are looked up case-insensitively. A name that is used for more than one
+/// address cannot be looked up, since it does not identify an address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    names: HashMap<Address, String>,
+    addresses: HashMap<String, Option<Address>>,
+}
+
+impl AddressBook {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn name(&self, address: &Address) -> Option<&str> {
+        self.names.get(address).map(String::as_str)
+    }
+
+    /// Returns the address with the given name, if the name is unique.
+    pub fn lookup(&self, name: &str) -> Option<Address> {
+        self.addresses.get(&name.to_lowercase()).copied().flatten()
+    }
+
+    /// Short label for graphs: the name or the short form of the address.
+    pub fn label(&self, address: &Address) -> String {
+        self.name(address)
+            .map_or_else(|| address.short(), str::to_string)
+    }
+
+    /// Label for explanations and logs: the checksummed address, preceded by the name if known.
+    pub fn describe(&self, address: &Address) -> String {
+        match self.name(address) {
+            Some(name) => format!("{name} ({})", address.to_checksummed_hex()),
+            None => address.to_checksummed_hex(),
+        }
+    }
+
+    /// Parses an address (validating the checksum) or, if that fails, looks up a name.
+    /// Returns the error of parsing the address if there is no such name.
+    pub fn resolve(&self, input: &str) -> Result<Address, AddressParseError> {
+        input
+            .parse()
+            .or_else(|e| self.lookup(input.trim()).ok_or(e))
+    }
+}
+
+impl From<HashMap<Address, String>> for AddressBook {
+    fn from(names: HashMap<Address, String>) -> Self {
+        let mut addresses: HashMap<String, Option<Address>> = HashMap::new();
+        for (address, name) in &names {
+            addresses
+                .entry(name.to_lowercase())
+                .and_modify(|a| *a = None)
+                .or_insert(Some(*address));
+        }
+        AddressBook { names, addresses }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let [a, b, c] = [1, 2, 3].map(|i| Address::from([i; 20]));
+        let book = AddressBook::from(HashMap::from([
+            (a, "Alice".to_string()),
+            (b, "bob".to_string()),
+            (c, "Bob".to_string()),
+        ]));
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.name(&a), Some("Alice"));
+        assert_eq!(book.lookup("alice"), Some(a));
+        // Ambiguous names do not resolve.
+        assert_eq!(book.lookup("Bob"), None);
+        assert_eq!(book.resolve("ALICE"), Ok(a));
+        assert_eq!(book.resolve(&format!("{b}")), Ok(b));
+        assert_eq!(
+            book.resolve("carol"),
+            Err(AddressParseError::InvalidLength(5))
+        );
+        assert_eq!(book.label(&a), "Alice");
+        assert_eq!(book.label(&Address::from([9; 20])), "0x090909");
+        assert_eq!(
+            book.describe(&a),
+            format!("Alice ({})", a.to_checksummed_hex())
+        );
+    }
+}
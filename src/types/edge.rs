@@ -1,13 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::types::Address;
 use crate::types::U256;
 
-#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(
+    Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize,
+)]
 pub struct Edge {
     pub from: Address,
     pub to: Address,
+    /// The owner of the token, serialized as `token_owner`.
+    #[serde(rename = "token_owner", alias = "token")]
     pub token: Address,
+    #[serde(alias = "value")]
     pub capacity: U256,
 }
 
@@ -89,6 +96,60 @@ impl EdgeDB {
     }
 }
 
+/// A difference between two edge databases for one (from, to, token) triple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeChange {
+    pub from: Address,
+    pub to: Address,
+    #[serde(rename = "token_owner")]
+    pub token: Address,
+    /// `None` if the edge was added.
+    pub old_capacity: Option<U256>,
+    /// `None` if the edge was removed.
+    pub new_capacity: Option<U256>,
+}
+
+/// Returns the edges that were added, removed or changed their capacity between
+/// `old` and `new`, ordered by (from, to, token).
+pub fn diff_edges(old: &EdgeDB, new: &EdgeDB) -> Vec<EdgeChange> {
+    let mut changes: BTreeMap<(Address, Address, Address), EdgeChange> = BTreeMap::new();
+    for (edges, is_new) in [(old, false), (new, true)] {
+        for e in edges.edges() {
+            let change = changes
+                .entry((e.from, e.to, e.token))
+                .or_insert(EdgeChange {
+                    from: e.from,
+                    to: e.to,
+                    token: e.token,
+                    old_capacity: None,
+                    new_capacity: None,
+                });
+            if is_new {
+                change.new_capacity = Some(e.capacity);
+            } else {
+                change.old_capacity = Some(e.capacity);
+            }
+        }
+    }
+    changes
+        .into_values()
+        .filter(|c| c.old_capacity != c.new_capacity)
+        .collect()
+}
+
+/// Serialized as the list of edges.
+impl Serialize for EdgeDB {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.edges.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EdgeDB {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(EdgeDB::new(Vec::<Edge>::deserialize(deserializer)?))
+    }
+}
+
 fn outgoing_index(edges: &[Edge]) -> HashMap<Address, Vec<usize>> {
     let mut index: HashMap<Address, Vec<usize>> = HashMap::new();
     for (i, e) in edges.iter().enumerate() {
@@ -104,3 +165,37 @@ fn incoming_index(edges: &[Edge]) -> HashMap<Address, Vec<usize>> {
     }
     index
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let [a, b, c] = [1, 2, 3].map(|i| Address::from([i; 20]));
+        let edge = |from, to, capacity: u128| Edge {
+            from,
+            to,
+            token: from,
+            capacity: U256::from(capacity),
+        };
+        let old = EdgeDB::new(vec![edge(a, b, 10), edge(b, c, 5), edge(c, a, 1)]);
+        let new = EdgeDB::new(vec![edge(c, a, 1), edge(a, b, 7), edge(a, c, 3)]);
+        let change = |from, to, old: Option<u128>, new: Option<u128>| EdgeChange {
+            from,
+            to,
+            token: from,
+            old_capacity: old.map(U256::from),
+            new_capacity: new.map(U256::from),
+        };
+        assert_eq!(
+            diff_edges(&old, &new),
+            vec![
+                change(a, b, Some(10), Some(7)),
+                change(a, c, None, Some(3)),
+                change(b, c, Some(5), None),
+            ]
+        );
+        assert!(diff_edges(&new, &new).is_empty());
+    }
+}
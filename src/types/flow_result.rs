@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Edge, U256};
+
+/// The result of a flow computation: the flow value and the transfers that achieve it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowResult {
+    pub flow: U256,
+    pub transfers: Vec<Edge>,
+}
+
+impl From<(U256, Vec<Edge>)> for FlowResult {
+    fn from((flow, transfers): (U256, Vec<Edge>)) -> Self {
+        FlowResult { flow, transfers }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Address;
+
+    #[test]
+    fn serde() {
+        let a = Address::from("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        let b = Address::from("0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359");
+        let result = FlowResult {
+            flow: U256::from(1000000000000000000),
+            transfers: vec![Edge {
+                from: a,
+                to: b,
+                token: a,
+                capacity: U256::from(1000000000000000000),
+            }],
+        };
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(
+            json,
+            "{\"flow\":\"1000000000000000000\",\"transfers\":[{\
+            \"from\":\"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed\",\
+            \"to\":\"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359\",\
+            \"token_owner\":\"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed\",\
+            \"capacity\":\"1000000000000000000\"}]}"
+        );
+        assert_eq!(serde_json::from_str::<FlowResult>(&json).unwrap(), result);
+    }
+}
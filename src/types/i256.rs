@@ -0,0 +1,191 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use super::U256;
+
+/// Signed integer with a 256 bit magnitude, in sign-magnitude representation.
+/// Used for capacity adjustments in the flow network, which can be negative.
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct I256 {
+    /// Never set for zero, so that there is a unique representation.
+    negative: bool,
+    magnitude: U256,
+}
+
+impl I256 {
+    pub const ZERO: I256 = I256 {
+        negative: false,
+        magnitude: U256::new(0, 0),
+    };
+
+    pub fn new(negative: bool, magnitude: U256) -> I256 {
+        I256 {
+            negative: negative && magnitude != U256::from(0),
+            magnitude,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn magnitude(&self) -> U256 {
+        self.magnitude
+    }
+
+    /// Returns `self + rhs`, or `None` if the magnitude of the result does not fit 256 bits.
+    pub fn checked_add(self, rhs: I256) -> Option<I256> {
+        Some(if self.negative == rhs.negative {
+            I256::new(self.negative, self.magnitude.checked_add(rhs.magnitude)?)
+        } else if self.magnitude >= rhs.magnitude {
+            I256::new(self.negative, self.magnitude - rhs.magnitude)
+        } else {
+            I256::new(rhs.negative, rhs.magnitude - self.magnitude)
+        })
+    }
+
+    /// Returns `self - rhs`, or `None` if the magnitude of the result does not fit 256 bits.
+    pub fn checked_sub(self, rhs: I256) -> Option<I256> {
+        self.checked_add(-rhs)
+    }
+
+    /// Returns the value as unsigned integer, or `None` if it is negative.
+    pub fn to_u256(self) -> Option<U256> {
+        if self.negative {
+            None
+        } else {
+            Some(self.magnitude)
+        }
+    }
+}
+
+impl From<U256> for I256 {
+    fn from(value: U256) -> Self {
+        I256::new(false, value)
+    }
+}
+
+impl Neg for I256 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        I256::new(!self.negative, self.magnitude)
+    }
+}
+
+/// Panics on overflow, also in release builds: the values are capacity adjustments,
+/// where wrapping around would silently produce a wrong flow.
+impl Add for I256 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).expect("I256 addition overflow")
+    }
+}
+
+impl AddAssign for I256 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for I256 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl SubAssign for I256 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for I256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for I256 {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", self.magnitude)
+    }
+}
+
+impl Debug for I256 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn i(v: i128) -> I256 {
+        I256::new(v < 0, U256::from(v.unsigned_abs()))
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(i(5) + i(-3), i(2));
+        assert_eq!(i(3) + i(-5), i(-2));
+        assert_eq!(i(-3) + i(-5), i(-8));
+        assert_eq!(i(3) - i(3), I256::ZERO);
+        assert_eq!(i(-3) + i(3), I256::ZERO);
+        assert!(!(i(-3) + i(3)).is_negative());
+        assert_eq!(-I256::ZERO, I256::ZERO);
+        let mut x = I256::from(U256::MAX);
+        x -= I256::from(U256::MAX);
+        assert_eq!(x, I256::ZERO);
+    }
+
+    #[test]
+    fn overflow() {
+        let max = I256::from(U256::MAX);
+        assert_eq!(max.checked_add(i(1)), None);
+        assert_eq!((-max).checked_sub(i(1)), None);
+        assert_eq!(
+            max.checked_add(i(-1)),
+            Some(I256::from(U256::MAX - U256::from(1)))
+        );
+        assert_eq!(max.checked_sub(max), Some(I256::ZERO));
+    }
+
+    #[test]
+    #[should_panic(expected = "I256 addition overflow")]
+    fn add_overflow() {
+        let _ = I256::from(U256::MAX) + i(1);
+    }
+
+    #[test]
+    fn compare() {
+        assert!(i(-5) < i(-3));
+        assert!(i(-3) < I256::ZERO);
+        assert!(I256::ZERO < i(2));
+        assert!(i(2) < I256::from(U256::MAX));
+        assert!(-I256::from(U256::MAX) < i(-2));
+    }
+
+    #[test]
+    fn to_u256() {
+        assert_eq!(i(7).to_u256(), Some(U256::from(7)));
+        assert_eq!(i(-7).to_u256(), None);
+        assert_eq!(format!("{}", i(-7)), "-0x7");
+    }
+}
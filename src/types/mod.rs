@@ -1,11 +1,17 @@
 pub mod address;
+pub mod address_book;
 pub mod edge;
+pub mod flow_result;
+pub mod i256;
 pub mod safe;
 pub mod token;
 pub mod u256;
 
 pub use address::Address;
+pub use address_book::AddressBook;
 pub use edge::Edge;
+pub use flow_result::FlowResult;
+pub use i256::I256;
 pub use safe::Safe;
 pub use token::Token;
-pub use u256::U256;
+pub use u256::{Rounding, CRC_DECIMALS, U256};
@@ -1,8 +1,10 @@
 use std::{cmp::min, collections::BTreeMap};
 
+use serde::{Deserialize, Serialize};
+
 use super::{Address, U256};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Safe {
     /// The address of the token, or the address of the safe if
     /// the database does not use the distinction.
@@ -26,11 +28,17 @@ impl Safe {
         } else {
             let receiver_balance = receiver.balance(&self.token_address);
 
-            let amount = (receiver.balance(&receiver.token_address)
-                * U256::from(trust_percentage as u128))
-                / U256::from(100);
-            let scaled_receiver_balance =
-                receiver_balance * U256::from((100 - trust_percentage) as u128) / U256::from(100);
+            // The Hub reverts if these multiplications overflow, so nothing can be sent.
+            let (Some(amount), Some(scaled_receiver_balance)) = (
+                receiver
+                    .balance(&receiver.token_address)
+                    .checked_mul(U256::from(trust_percentage as u128)),
+                receiver_balance.checked_mul(U256::from((100 - trust_percentage) as u128)),
+            ) else {
+                return U256::from(0);
+            };
+            let amount = amount / U256::from(100);
+            let scaled_receiver_balance = scaled_receiver_balance / U256::from(100);
             if amount < receiver_balance {
                 U256::from(0)
             } else {
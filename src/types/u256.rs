@@ -1,15 +1,33 @@
+use std::error::Error;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::ops::Div;
 use std::ops::Mul;
 use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
 
 use num_bigint::BigUint;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
 
 #[derive(Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct U256([u128; 2]);
 
+/// Number of decimals of Circles tokens.
+pub const CRC_DECIMALS: u8 = 18;
+
+/// How to round when a decimal number has more fractional digits than can be represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Towards zero.
+    Down,
+    /// Away from zero.
+    Up,
+    /// To the nearest value, halfway cases away from zero.
+    Nearest,
+}
+
 impl U256 {
     pub const fn new(high: u128, low: u128) -> U256 {
         U256([high, low])
@@ -30,6 +48,11 @@ impl U256 {
         format!("{value}")
     }
 
+    /// Approximate value as a floating point number, for statistics.
+    pub fn to_f64(self) -> f64 {
+        self.0[0] as f64 * 2f64.powi(128) + self.0[1] as f64
+    }
+
     pub fn to_decimal_fraction(self) -> String {
         let value: BigUint = self.into();
         let formatted = format!("{value}");
@@ -48,6 +71,175 @@ impl U256 {
         }
     }
 
+    pub fn overflowing_add(self, rhs: U256) -> (U256, bool) {
+        let (low, carry) = self.0[1].overflowing_add(rhs.0[1]);
+        let (high, overflow1) = self.0[0].overflowing_add(rhs.0[0]);
+        let (high, overflow2) = high.overflowing_add(carry as u128);
+        (U256([high, low]), overflow1 || overflow2)
+    }
+
+    pub fn overflowing_sub(self, rhs: U256) -> (U256, bool) {
+        let (low, borrow) = self.0[1].overflowing_sub(rhs.0[1]);
+        let (high, overflow1) = self.0[0].overflowing_sub(rhs.0[0]);
+        let (high, overflow2) = high.overflowing_sub(borrow as u128);
+        (U256([high, low]), overflow1 || overflow2)
+    }
+
+    pub fn overflowing_mul(self, rhs: U256) -> (U256, bool) {
+        let product = BigUint::from(self) * BigUint::from(rhs);
+        let overflow = product.bits() > 256;
+        (U256::from_bigint_truncating(product), overflow)
+    }
+
+    /// Two's complement negation, overflows for all values except zero.
+    pub fn overflowing_neg(self) -> (U256, bool) {
+        U256::from(0).overflowing_sub(self)
+    }
+
+    pub fn checked_add(self, rhs: U256) -> Option<U256> {
+        checked(self.overflowing_add(rhs))
+    }
+
+    pub fn checked_sub(self, rhs: U256) -> Option<U256> {
+        checked(self.overflowing_sub(rhs))
+    }
+
+    pub fn checked_mul(self, rhs: U256) -> Option<U256> {
+        checked(self.overflowing_mul(rhs))
+    }
+
+    /// Returns `None` if `rhs` is zero.
+    pub fn checked_div(self, rhs: U256) -> Option<U256> {
+        if rhs == U256::from(0) {
+            None
+        } else {
+            Some(self / rhs)
+        }
+    }
+
+    pub fn saturating_add(self, rhs: U256) -> U256 {
+        self.checked_add(rhs).unwrap_or(U256::MAX)
+    }
+
+    pub fn saturating_sub(self, rhs: U256) -> U256 {
+        self.checked_sub(rhs).unwrap_or_default()
+    }
+
+    pub fn saturating_mul(self, rhs: U256) -> U256 {
+        self.checked_mul(rhs).unwrap_or(U256::MAX)
+    }
+
+    pub fn wrapping_add(self, rhs: U256) -> U256 {
+        self.overflowing_add(rhs).0
+    }
+
+    pub fn wrapping_sub(self, rhs: U256) -> U256 {
+        self.overflowing_sub(rhs).0
+    }
+
+    pub fn wrapping_mul(self, rhs: U256) -> U256 {
+        self.overflowing_mul(rhs).0
+    }
+
+    pub fn wrapping_neg(self) -> U256 {
+        self.overflowing_neg().0
+    }
+
+    /// Parses a decimal number with an optional fractional part (e.g. "12.5") into
+    /// an integer in units of `10^-decimals`, rounding excess fractional digits.
+    pub fn from_decimal_units(
+        input: &str,
+        decimals: u8,
+        rounding: Rounding,
+    ) -> Result<U256, U256ParseError> {
+        let (integer, fraction) = input.split_once('.').unwrap_or((input, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(U256ParseError::Empty);
+        }
+        if let Some(c) = integer
+            .chars()
+            .chain(fraction.chars())
+            .find(|c| !c.is_ascii_digit())
+        {
+            return Err(U256ParseError::InvalidCharacter(c));
+        }
+        let decimals = decimals as usize;
+        let (fraction, excess) = fraction.split_at(fraction.len().min(decimals));
+        let digits = format!(
+            "{integer}{fraction}{}",
+            "0".repeat(decimals - fraction.len())
+        );
+        let mut value = digits.parse::<BigUint>().unwrap_or_default();
+        let round_up = match rounding {
+            Rounding::Down => false,
+            Rounding::Up => excess.chars().any(|c| c != '0'),
+            Rounding::Nearest => excess.chars().next().is_some_and(|c| c >= '5'),
+        };
+        if round_up {
+            value += 1u32;
+        }
+        if value.bits() > 256 {
+            return Err(U256ParseError::Overflow);
+        }
+        Ok(U256::from_bigint_truncating(value))
+    }
+
+    /// Formats the value, interpreted in units of `10^-decimals`, as a decimal number
+    /// with at most `fraction_digits` fractional digits. Trailing zeros are omitted.
+    pub fn to_decimal_units(self, decimals: u8, fraction_digits: u8, rounding: Rounding) -> String {
+        let value: BigUint = self.into();
+        let fraction_digits = fraction_digits.min(decimals) as u32;
+        let divisor = BigUint::from(10u32).pow(decimals as u32 - fraction_digits);
+        let (mut rounded, remainder) = (&value / &divisor, &value % &divisor);
+        let round_up = match rounding {
+            Rounding::Down => false,
+            Rounding::Up => remainder != BigUint::from(0u32),
+            Rounding::Nearest => remainder * 2u32 >= divisor,
+        };
+        if round_up {
+            rounded += 1u32;
+        }
+        let scale = BigUint::from(10u32).pow(fraction_digits);
+        let fraction = format!(
+            "{:0>width$}",
+            (&rounded % &scale).to_string(),
+            width = fraction_digits as usize
+        );
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            format!("{}", rounded / scale)
+        } else {
+            format!("{}.{fraction}", rounded / scale)
+        }
+    }
+
+    /// Parses an amount given either in raw units (decimal or `0x`-prefixed hex)
+    /// or in CRC, if it contains a decimal point or ends in "CRC" (e.g. "12.5", "3 CRC").
+    /// CRC amounts are rounded down to the smallest unit.
+    pub fn parse_amount(input: &str) -> Result<U256, U256ParseError> {
+        let input = input.trim();
+        match input.strip_suffix("CRC") {
+            Some(crc) => U256::from_decimal_units(crc.trim_end(), CRC_DECIMALS, Rounding::Down),
+            None if input.contains('.') => {
+                U256::from_decimal_units(input, CRC_DECIMALS, Rounding::Down)
+            }
+            None => input.parse(),
+        }
+    }
+
+    /// Formats the value as CRC amount with full precision.
+    pub fn to_crc(self) -> String {
+        self.to_decimal_units(CRC_DECIMALS, CRC_DECIMALS, Rounding::Down)
+    }
+
+    /// Returns the 32-byte big-endian representation.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        result[..16].copy_from_slice(&self.0[0].to_be_bytes());
+        result[16..].copy_from_slice(&self.0[1].to_be_bytes());
+        result
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut result = Vec::new();
         for i in 0..=1 {
@@ -62,55 +254,94 @@ impl U256 {
     }
 }
 
+fn checked((value, overflow): (U256, bool)) -> Option<U256> {
+    if overflow {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Result of an arithmetic operator. Operators wrap around on overflow, like
+/// the primitive integer types in release builds. With the `overflow-checks`
+/// feature, they panic on overflow in debug builds instead.
+fn operator_result((value, overflow): (U256, bool), operation: &str) -> U256 {
+    if cfg!(all(feature = "overflow-checks", debug_assertions)) && overflow {
+        panic!("U256 {operation} overflow");
+    }
+    value
+}
+
 impl From<u128> for U256 {
     fn from(item: u128) -> Self {
         U256([0, item])
     }
 }
 
-// TODO str is using unicode stuff - maybe we should use Vec<u8> for efficiency reasons?
-impl From<&str> for U256 {
-    fn from(item: &str) -> Self {
-        if item.starts_with("0x") {
-            let len = item.len();
-            assert!(len <= 2 + 32 + 32, "{}", len);
-            let low_start = if len >= 2 + 32 { len - 32 } else { 2 };
-            let low_hex = &item[low_start..];
-            // disallow + and - prefixes
-            assert!(
-                low_hex.as_bytes().first() != Some(&54) && low_hex.as_bytes().first() != Some(&43)
-            );
-            let low = if low_hex.is_empty() {
-                0
-            } else {
-                u128::from_str_radix(low_hex, 16).unwrap()
-            };
-            let high_start = if len >= 2 + 32 + 32 { len - 64 } else { 2 };
-            let high_hex = &item[high_start..low_start];
-            // disallow + and - prefixes
-            assert!(
-                high_hex.as_bytes().first() != Some(&45)
-                    && high_hex.as_bytes().first() != Some(&43)
-            );
-            let high = if high_hex.is_empty() {
-                0
-            } else {
-                u128::from_str_radix(high_hex, 16).unwrap()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum U256ParseError {
+    Empty,
+    InvalidCharacter(char),
+    Overflow,
+}
+
+impl Display for U256ParseError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            U256ParseError::Empty => write!(f, "Empty number"),
+            U256ParseError::InvalidCharacter(c) => write!(f, "Invalid digit '{c}'"),
+            U256ParseError::Overflow => write!(f, "Number does not fit 256 bits"),
+        }
+    }
+}
+
+impl Error for U256ParseError {}
+
+/// Parses a decimal number or a `0x`-prefixed hex number.
+impl FromStr for U256 {
+    type Err = U256ParseError;
+    fn from_str(item: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = item.strip_prefix("0x") {
+            if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+                return Err(U256ParseError::InvalidCharacter(c));
+            }
+            let hex = hex.trim_start_matches('0');
+            if hex.len() > 64 {
+                return Err(U256ParseError::Overflow);
+            }
+            let low_start = hex.len().saturating_sub(32);
+            let parse = |digits: &str| {
+                if digits.is_empty() {
+                    0
+                } else {
+                    u128::from_str_radix(digits, 16).unwrap()
+                }
             };
-            U256([high, low])
+            Ok(U256([parse(&hex[..low_start]), parse(&hex[low_start..])]))
         } else {
-            let digits = item.parse::<num_bigint::BigUint>().unwrap().to_u64_digits();
-            assert!(digits.len() <= 4);
-            U256([
-                u128::from(*digits.get(3).unwrap_or(&0)) << 64
-                    | u128::from(*digits.get(2).unwrap_or(&0)),
-                u128::from(*digits.get(1).unwrap_or(&0)) << 64
-                    | u128::from(*digits.first().unwrap_or(&0)),
-            ])
+            if item.is_empty() {
+                return Err(U256ParseError::Empty);
+            }
+            if let Some(c) = item.chars().find(|c| !c.is_ascii_digit()) {
+                return Err(U256ParseError::InvalidCharacter(c));
+            }
+            let value = item.parse::<BigUint>().unwrap();
+            if value.bits() > 256 {
+                return Err(U256ParseError::Overflow);
+            }
+            Ok(U256::from_bigint_truncating(value))
         }
     }
 }
 
+/// Conversion that panics on invalid input, use `str::parse` to handle errors.
+impl From<&str> for U256 {
+    fn from(item: &str) -> Self {
+        item.parse()
+            .unwrap_or_else(|e| panic!("Invalid number {item}: {e}"))
+    }
+}
+
 impl From<U256> for BigUint {
     fn from(value: U256) -> Self {
         BigUint::from(value.0[0]) << 128 | BigUint::from(value.0[1])
@@ -120,12 +351,7 @@ impl From<U256> for BigUint {
 impl Add for U256 {
     type Output = Self;
     fn add(self, rhs: Self) -> Self {
-        let (low, carry) = self.0[1].overflowing_add(rhs.0[1]);
-        let mut high = self.0[0].wrapping_add(rhs.0[0]);
-        if carry {
-            high = high.wrapping_add(1);
-        }
-        Self([high, low])
+        operator_result(self.overflowing_add(rhs), "addition")
     }
 }
 
@@ -138,15 +364,14 @@ impl AddAssign for U256 {
 impl Neg for U256 {
     type Output = Self;
     fn neg(self) -> Self {
-        let result = U256([!self.0[0], !self.0[1]]);
-        result + U256::from(1)
+        operator_result(self.overflowing_neg(), "negation")
     }
 }
 
 impl Sub for U256 {
     type Output = Self;
     fn sub(self, rhs: Self) -> Self {
-        self + (-rhs)
+        operator_result(self.overflowing_sub(rhs), "subtraction")
     }
 }
 
@@ -154,9 +379,7 @@ impl Mul for U256 {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let self_big: BigUint = self.into();
-        let rhs_big: BigUint = rhs.into();
-        U256::from_bigint_truncating(self_big * rhs_big)
+        operator_result(self.overflowing_mul(rhs), "multiplication")
     }
 }
 
@@ -192,9 +415,44 @@ impl Debug for U256 {
     }
 }
 
+/// Serializes as a decimal string, since JSON numbers cannot represent 256 bit values.
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_decimal())
+    }
+}
+
+struct U256Visitor;
+
+impl<'de> Visitor<'de> for U256Visitor {
+    type Value = U256;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "a decimal or 0x-prefixed hex string or an unsigned integer"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<U256, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<U256, E> {
+        Ok(U256::from(v as u128))
+    }
+}
+
+/// Deserializes from a decimal or hex string or from an unsigned integer.
+impl<'de> Deserialize<'de> for U256 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(U256Visitor)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::U256;
+    use super::{U256ParseError, U256};
     #[test]
     fn to_string() {
         assert_eq!(format!("{}", U256::from(0)), "0x0");
@@ -258,6 +516,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_errors() {
+        assert_eq!("".parse::<U256>(), Err(U256ParseError::Empty));
+        assert_eq!(
+            "12a".parse::<U256>(),
+            Err(U256ParseError::InvalidCharacter('a'))
+        );
+        assert_eq!(
+            "0x-1".parse::<U256>(),
+            Err(U256ParseError::InvalidCharacter('-'))
+        );
+        assert_eq!("0x6".parse::<U256>(), Ok(U256::from(6)));
+        assert_eq!(
+            format!("0x1{}", "0".repeat(64)).parse::<U256>(),
+            Err(U256ParseError::Overflow)
+        );
+        assert_eq!(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+                .parse::<U256>(),
+            Err(U256ParseError::Overflow)
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let value = U256::from(u128::MAX) + U256::from(1);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"340282366920938463463374607431768211456\"");
+        assert_eq!(serde_json::from_str::<U256>(&json).unwrap(), value);
+        assert_eq!(
+            serde_json::from_str::<U256>("\"0x100000000000000000000000000000000\"").unwrap(),
+            value
+        );
+        assert_eq!(serde_json::from_str::<U256>("17").unwrap(), U256::from(17));
+        assert!(serde_json::from_str::<U256>("-1").is_err());
+    }
+
     #[test]
     fn to_decimal() {
         assert_eq!(U256::from("0").to_decimal(), "0");
@@ -273,6 +568,8 @@ mod test {
             U256::from("340282366920938463463374607431768211456").to_decimal(),
             "340282366920938463463374607431768211456"
         );
+        assert_eq!(U256::from(1234).to_f64(), 1234.0);
+        assert_eq!(U256::new(1, 0).to_f64(), 2f64.powi(128));
     }
 
     #[test]
@@ -287,6 +584,8 @@ mod test {
             large / three,
             U256::from("0x55555555555555555555555555555555")
         );
+        // Without the `overflow-checks` feature, operators wrap around.
+        #[cfg(not(all(feature = "overflow-checks", debug_assertions)))]
         assert_eq!(large * large, U256::from("0"));
         assert_eq!(
             (large / two) * large,
@@ -294,6 +593,123 @@ mod test {
         );
     }
 
+    #[test]
+    fn checked_arithmetic() {
+        let one = U256::from(1);
+        let large = U256::from("0x100000000000000000000000000000000");
+        assert_eq!(U256::MAX.overflowing_add(one), (U256::from(0), true));
+        assert_eq!(U256::from(u128::MAX).overflowing_add(one), (large, false));
+        assert_eq!(U256::from(0).overflowing_sub(one), (U256::MAX, true));
+        assert_eq!(large.overflowing_sub(one), (U256::from(u128::MAX), false));
+        assert_eq!(large.overflowing_mul(large), (U256::from(0), true));
+        assert_eq!(one.overflowing_neg(), (U256::MAX, true));
+        assert_eq!(U256::from(0).overflowing_neg(), (U256::from(0), false));
+
+        assert_eq!(U256::MAX.checked_add(one), None);
+        assert_eq!(one.checked_sub(U256::from(2)), None);
+        assert_eq!(large.checked_mul(large), None);
+        assert_eq!(one.checked_div(U256::from(0)), None);
+        assert_eq!(
+            U256::from(7).checked_div(U256::from(2)),
+            Some(U256::from(3))
+        );
+
+        assert_eq!(U256::MAX.saturating_add(one), U256::MAX);
+        assert_eq!(one.saturating_sub(U256::from(2)), U256::from(0));
+        assert_eq!(large.saturating_mul(large), U256::MAX);
+
+        assert_eq!(U256::MAX.wrapping_add(U256::from(2)), one);
+        assert_eq!(U256::from(0).wrapping_sub(one), U256::MAX);
+        assert_eq!(one.wrapping_neg().wrapping_add(one), U256::from(0));
+    }
+
+    #[test]
+    #[cfg(all(feature = "overflow-checks", debug_assertions))]
+    #[should_panic(expected = "U256 subtraction overflow")]
+    fn operator_overflow_check() {
+        let _ = U256::from(0) - U256::from(1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "overflow-checks", debug_assertions))]
+    #[should_panic(expected = "U256 multiplication overflow")]
+    fn mul_overflow_check() {
+        let large = U256::from("0x100000000000000000000000000000000");
+        let _ = large * large;
+    }
+
+    #[test]
+    fn wrapping_mul() {
+        let large = U256::from("0x100000000000000000000000000000000");
+        assert_eq!(large.wrapping_mul(large), U256::from("0"));
+        assert_eq!(
+            U256::MAX.wrapping_mul(U256::from(2)),
+            U256::MAX - U256::from(1)
+        );
+        assert_eq!(
+            (large + U256::from(3)).wrapping_mul(large),
+            U256::from(3) * large
+        );
+    }
+
+    #[test]
+    fn decimal_units() {
+        use super::{Rounding, CRC_DECIMALS};
+        let parse = |s: &str, rounding| U256::from_decimal_units(s, 3, rounding);
+        assert_eq!(parse("12.5", Rounding::Down), Ok(U256::from(12500)));
+        assert_eq!(parse("12", Rounding::Down), Ok(U256::from(12000)));
+        assert_eq!(parse(".25", Rounding::Down), Ok(U256::from(250)));
+        assert_eq!(parse("1.", Rounding::Down), Ok(U256::from(1000)));
+        assert_eq!(parse("0.0015", Rounding::Down), Ok(U256::from(1)));
+        assert_eq!(parse("0.0015", Rounding::Up), Ok(U256::from(2)));
+        assert_eq!(parse("0.0010", Rounding::Up), Ok(U256::from(1)));
+        assert_eq!(parse("0.0015", Rounding::Nearest), Ok(U256::from(2)));
+        assert_eq!(parse("0.0014", Rounding::Nearest), Ok(U256::from(1)));
+        assert_eq!(parse(".", Rounding::Down), Err(U256ParseError::Empty));
+        assert_eq!(
+            parse("1.2.3", Rounding::Down),
+            Err(U256ParseError::InvalidCharacter('.'))
+        );
+        assert_eq!(
+            U256::from_decimal_units(&U256::MAX.to_decimal(), 1, Rounding::Down),
+            Err(U256ParseError::Overflow)
+        );
+
+        let value = U256::from(12345);
+        assert_eq!(value.to_decimal_units(3, 3, Rounding::Down), "12.345");
+        assert_eq!(value.to_decimal_units(3, 1, Rounding::Down), "12.3");
+        assert_eq!(value.to_decimal_units(3, 1, Rounding::Up), "12.4");
+        assert_eq!(value.to_decimal_units(3, 2, Rounding::Nearest), "12.35");
+        assert_eq!(value.to_decimal_units(3, 0, Rounding::Nearest), "12");
+        assert_eq!(
+            U256::from(12000).to_decimal_units(3, 3, Rounding::Down),
+            "12"
+        );
+        assert_eq!(
+            U256::from(5).to_decimal_units(3, 5, Rounding::Down),
+            "0.005"
+        );
+        assert_eq!(
+            U256::MAX.to_decimal_units(CRC_DECIMALS, 0, Rounding::Up),
+            "115792089237316195423570985008687907853269984665640564039458"
+        );
+    }
+
+    #[test]
+    fn amounts() {
+        let crc = U256::from(1000000000000000000);
+        assert_eq!(
+            U256::parse_amount("12.5"),
+            Ok(crc * U256::from(25) / U256::from(2))
+        );
+        assert_eq!(U256::parse_amount("3 CRC"), Ok(crc * U256::from(3)));
+        assert_eq!(U256::parse_amount("3CRC"), Ok(crc * U256::from(3)));
+        assert_eq!(U256::parse_amount("3"), Ok(U256::from(3)));
+        assert_eq!(U256::parse_amount("0x10"), Ok(U256::from(16)));
+        assert_eq!((crc * U256::from(25) / U256::from(2)).to_crc(), "12.5");
+        assert_eq!(U256::from(1).to_crc(), "0.000000000000000001");
+    }
+
     #[test]
     fn to_bytes() {
         let zero = U256::from("0");
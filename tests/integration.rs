@@ -1,49 +1,161 @@
+use std::collections::BTreeMap;
+
 use pathfinder2::graph::compute_flow;
-use pathfinder2::io::import_from_safes_binary;
+use pathfinder2::io::{
+    detect_format, import_from_safes_binary, read_edges_file, write_edges_binary, write_edges_csv,
+    write_edges_json, write_safes_binary, FileFormat,
+};
+use pathfinder2::replay::HubState;
+use pathfinder2::safe_db::db::DB;
 use pathfinder2::types::edge::EdgeDB;
-use pathfinder2::types::{Address, U256};
-use std::process::Command;
-
-const HUB_ADDRESS: &str = "0x29b9a7fBb8995b2423a71cC17cf9810798F6C543";
-const TRANSFER_THROUGH_SIG: &str = "transferThrough(address[],address[],address[],uint256[])";
-const RPC_URL: &str = "https://rpc.circlesubi.id";
+use pathfinder2::types::{Address, Safe, U256};
 
 #[test]
+#[ignore = "needs capacity_graph.db, run with --ignored"]
 fn test_flow_chris_martin() {
-    let edges = read_edges();
+    let (state, edges) = read_state();
     let chriseth = Address::from("0x8DC7e86fF693e9032A0F41711b5581a04b26Be2E");
     let martin = Address::from("0x42cEDde51198D1773590311E2A340DC06B24cB37");
-    test_flow(&chriseth, &martin, &edges, U256::MAX, None);
-    test_flow(&chriseth, &martin, &edges, U256::MAX, Some(2));
+    test_flow(&chriseth, &martin, &state, &edges, U256::MAX, None);
+    test_flow(&chriseth, &martin, &state, &edges, U256::MAX, Some(2));
     test_flow(
         &chriseth,
         &martin,
+        &state,
         &edges,
         U256::from(71152921504606846976),
         Some(2),
     );
-    test_flow(&chriseth, &martin, &read_edges(), U256::MAX, Some(2));
 }
 
 #[test]
+#[ignore = "needs capacity_graph.db, run with --ignored"]
 fn test_flow_large() {
-    let edges = read_edges();
+    let (state, edges) = read_state();
     let large_source = Address::from("0x9BA1Bcd88E99d6E1E03252A70A63FEa83Bf1208c");
     let large_dest = Address::from("0x939b2731997922f21ab0a0bab500a949c0fc3550");
-    test_flow(&large_source, &large_dest, &edges, U256::MAX, Some(4));
-    test_flow(&large_source, &large_dest, &edges, U256::MAX, Some(6));
+    test_flow(
+        &large_source,
+        &large_dest,
+        &state,
+        &edges,
+        U256::MAX,
+        Some(4),
+    );
+    test_flow(
+        &large_source,
+        &large_dest,
+        &state,
+        &edges,
+        U256::MAX,
+        Some(6),
+    );
 }
 
-fn read_edges() -> EdgeDB {
-    import_from_safes_binary("capacity_graph.db")
-        .unwrap()
-        .edges()
-        .clone()
+#[test]
+fn test_flow_snapshot() {
+    let ([a, _, c, org], db) = snapshot_db();
+    let path = temp_path("hub_state");
+    write_safes_binary(&db, &path).unwrap();
+    let state = HubState::from_snapshot(&path).unwrap();
+    let edges = import_from_safes_binary(&path).unwrap().edges().clone();
+    std::fs::remove_file(&path).unwrap();
+
+    test_flow(&a, &c, &state, &edges, U256::MAX, None);
+    test_flow(&a, &org, &state, &edges, U256::MAX, None);
+    test_flow(&c, &a, &state, &edges, U256::MAX, Some(1));
+}
+
+#[test]
+fn test_detect_format() {
+    let (_, db) = snapshot_db();
+    let path = temp_path("formats");
+    write_safes_binary(&db, &path).unwrap();
+    let edges = import_from_safes_binary(&path).unwrap().edges().clone();
+    assert_eq!(detect_format(&path).unwrap(), FileFormat::SafesBinary);
+    assert_eq!(read_edges_file(&path).unwrap().edges(), edges.edges());
+    for (write, format) in [
+        (
+            write_edges_binary as fn(&EdgeDB, &String) -> _,
+            FileFormat::EdgesBinary,
+        ),
+        (write_edges_csv, FileFormat::EdgesCsv),
+        (write_edges_json, FileFormat::EdgesJson),
+    ] {
+        write(&edges, &path).unwrap();
+        assert_eq!(detect_format(&path).unwrap(), format);
+        assert_eq!(
+            read_edges_file(&path).unwrap().edge_count(),
+            edges.edge_count()
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("pathfinder2_{name}_{}.db", std::process::id()))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn snapshot_db() -> ([Address; 4], DB) {
+    let a = Address::from("0x11C7e86fF693e9032A0F41711b5581a04b26Be2E");
+    let b = Address::from("0x22cEDde51198D1773590311E2A340DC06B24cB37");
+    let c = Address::from("0x33cEDde51198D1773590311E2A340DC06B24cB37");
+    let org = Address::from("0x44cEDde51198D1773590311E2A340DC06B24cB37");
+    let ether = U256::from(1000000000000000000);
+    // b trusts a, c trusts b and the organization accepts c's tokens.
+    let safes = BTreeMap::from([
+        (
+            a,
+            Safe {
+                token_address: a,
+                balances: [(a, ether * U256::from(100))].into(),
+                limit_percentage: [(b, 50)].into(),
+                organization: false,
+            },
+        ),
+        (
+            b,
+            Safe {
+                token_address: b,
+                balances: [(b, ether * U256::from(100))].into(),
+                limit_percentage: [(c, 20)].into(),
+                organization: false,
+            },
+        ),
+        (
+            c,
+            Safe {
+                token_address: c,
+                balances: [(c, ether * U256::from(30)), (a, ether * U256::from(5))].into(),
+                limit_percentage: [(org, 100)].into(),
+                organization: false,
+            },
+        ),
+        (
+            org,
+            Safe {
+                token_address: org,
+                organization: true,
+                ..Default::default()
+            },
+        ),
+    ]);
+    let token_owner = BTreeMap::from([(a, a), (b, b), (c, c), (org, org)]);
+    ([a, b, c, org], DB::new(safes, token_owner))
+}
+
+fn read_state() -> (HubState, EdgeDB) {
+    let db = import_from_safes_binary("capacity_graph.db").unwrap();
+    (HubState::from_db(&db), db.edges().clone())
 }
 
 fn test_flow(
     source: &Address,
     sink: &Address,
+    state: &HubState,
     edges: &EdgeDB,
     requested_flow: U256,
     max_distance: Option<u64>,
@@ -51,49 +163,13 @@ fn test_flow(
     let transfers = compute_flow(source, sink, edges, requested_flow, max_distance, None);
     println!("{transfers:?}");
 
-    let token_owners = transfers
-        .1
-        .iter()
-        .map(|e| e.token.to_string())
-        .collect::<Vec<String>>()
-        .join(",");
-    let froms = transfers
-        .1
-        .iter()
-        .map(|e| e.from.to_string())
-        .collect::<Vec<String>>()
-        .join(",");
-    let tos = transfers
-        .1
-        .iter()
-        .map(|e| e.to.to_string())
-        .collect::<Vec<String>>()
-        .join(",");
-    let amounts = transfers
-        .1
-        .iter()
-        .map(|e| e.capacity.to_decimal())
-        .collect::<Vec<String>>()
-        .join(",");
-    let output = Command::new("cast")
-        .args([
-            "call",
-            HUB_ADDRESS,
-            TRANSFER_THROUGH_SIG,
-            &format!("[{token_owners}]"),
-            &format!("[{froms}]"),
-            &format!("[{tos}]"),
-            &format!("[{amounts}]"),
-            "--rpc-url",
-            RPC_URL,
-            "--from",
-            &transfers.1[0].from.to_string(),
-        ])
-        .output()
-        .expect("Error calling cast.");
-    let stdout = String::from_utf8(output.stdout).unwrap().trim().to_string();
-    let stderr = String::from_utf8(output.stderr).unwrap().trim().to_string();
-    println!("Transfer: {stdout} {stderr}",);
-    assert_eq!(stdout, "0x".to_string());
-    assert!(stderr.is_empty());
+    assert!(!transfers.1.is_empty());
+    let summary = state
+        .clone()
+        .transfer_through(source, &transfers.1)
+        .unwrap_or_else(|e| panic!("Transfer would revert: {e}"));
+    println!("Transfer: {summary:?}");
+    assert_eq!(summary.from, *source);
+    assert_eq!(summary.to, *sink);
+    assert_eq!(summary.amount, transfers.0);
 }